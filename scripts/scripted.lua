@@ -0,0 +1,40 @@
+-- Steering terms get the boid and its neighbours and return a force table {x, y}.
+function orbit(boid, neighbours)
+    local cx, cy = 1000.0, 1000.0
+    local dx, dy = boid.x - cx, boid.y - cy
+    local dist = math.sqrt(dx * dx + dy * dy)
+    if dist < 1.0 then
+        return nil
+    end
+    -- Tangential pull around the centre, plus a gentle spring back to a 400 unit ring.
+    local spring = (400.0 - dist) / dist * 0.01
+    return { x = -dy / dist + dx * spring, y = dx / dist + dy * spring }
+end
+
+function keep_apart(boid, neighbours)
+    local fx, fy = 0.0, 0.0
+    for _, other in ipairs(neighbours) do
+        local dx, dy = boid.x - other.x, boid.y - other.y
+        local dist2 = dx * dx + dy * dy
+        if dist2 > 0.0 and dist2 < 400.0 then
+            fx = fx + dx / dist2
+            fy = fy + dy / dist2
+        end
+    end
+    return { x = fx * 10.0, y = fy * 10.0 }
+end
+
+-- Timed events are called with the simulation time in seconds.
+function release_predator(t)
+    print(string.format("[%.1fs] releasing a predator", t))
+    spawn("predator", 1000.0, 1900.0)
+end
+
+function start_rain(t)
+    set_weather("Rainy", 12.0)
+end
+
+function drop_obstacle(t)
+    add_obstacle(1000.0, 1000.0, 80.0)
+    set_parameter("cohesion", 1.5, "prey")
+end
@@ -0,0 +1,27 @@
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::ecs::prelude::{
+    Component,
+    VecStorage,
+};
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Age {
+    seconds: f32,
+}
+
+impl Age {
+    pub fn advance(&mut self, seconds: f32) {
+        self.seconds += seconds;
+    }
+
+    pub fn get_seconds(&self) -> f32 {
+        self.seconds
+    }
+}
+
+impl Component for Age {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,382 @@
+use std::{
+    io::{
+        self,
+        Read,
+        Write,
+    },
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+    sync::{
+        mpsc::{
+            self,
+            Receiver,
+            Sender,
+        },
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use tungstenite::{
+    Message,
+    WebSocket,
+};
+use crate::{
+    flock::FlockSummary,
+    metrics::Metrics,
+    recording::BoidState,
+    resources::{
+        WeatherType,
+        WorldConfig,
+    },
+    scenario::Obstacle,
+    sweep::SweepParameter,
+};
+
+const VIEWER: &str = include_str!("../viewer/index.html");
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const MAX_REQUEST: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ApiCommand {
+    Pause,
+    Resume,
+    SetParameter {
+        parameter: SweepParameter,
+        #[serde(default)]
+        species: Option<String>,
+        value: f32,
+    },
+    Spawn {
+        position: (f32, f32),
+        #[serde(default)]
+        velocity: (f32, f32),
+        #[serde(default)]
+        species: Option<String>,
+        #[serde(default = "ApiCommand::default_count")]
+        count: usize,
+    },
+    AddObstacle {
+        position: (f32, f32),
+        radius: f32,
+    },
+    ClearObstacles,
+    SetWeather {
+        weather: WeatherType,
+    },
+}
+
+impl ApiCommand {
+    fn default_count() -> usize {
+        1
+    }
+
+    // NaN or out-of-world values would end up in the environment and poison steering.
+    pub fn check(&self, world: Option<&WorldConfig>) -> Result<(), String> {
+        let finite = |(x, y): (f32, f32)| x.is_finite() && y.is_finite();
+        let inside = |(x, y): (f32, f32)| world.map_or(true, |world| x >= 0.0 && x < world.width && y >= 0.0 && y < world.height);
+        match self {
+            ApiCommand::SetParameter { value, .. } if !value.is_finite() => {
+                Err(format!("parameter value {} is not a finite number", value))
+            },
+            ApiCommand::Spawn { position, velocity, .. } => {
+                if !finite(*position) || !finite(*velocity) {
+                    Err("spawn position and velocity must be finite numbers".to_string())
+                }
+                else if !inside(*position) {
+                    Err(format!("spawn position {:?} lies outside the world", position))
+                }
+                else {
+                    Ok(())
+                }
+            },
+            ApiCommand::AddObstacle { position, radius } => {
+                if !finite(*position) || !radius.is_finite() {
+                    Err("obstacle position and radius must be finite numbers".to_string())
+                }
+                else if *radius <= 0.0 {
+                    Err(format!("obstacle radius {} must be positive", radius))
+                }
+                else if !inside(*position) {
+                    Err(format!("obstacle position {:?} lies outside the world", position))
+                }
+                else {
+                    Ok(())
+                }
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PendingCommands(pub Vec<ApiCommand>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Telemetry {
+    pub tick: u64,
+    pub time: f64,
+    pub paused: bool,
+    pub weather: WeatherType,
+    pub world: WorldConfig,
+    pub species: Vec<String>,
+    pub obstacles: Vec<Obstacle>,
+    pub metrics: Metrics,
+    pub flocks: Vec<FlockSummary>,
+    pub boids: Vec<BoidState>,
+}
+
+#[derive(Clone)]
+struct Shared {
+    commands: Sender<ApiCommand>,
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    latest: Arc<Mutex<String>>,
+    world: Arc<Mutex<Option<WorldConfig>>>,
+}
+
+pub struct ApiServer {
+    commands: Mutex<Receiver<ApiCommand>>,
+    clients: Arc<Mutex<Vec<Sender<String>>>>,
+    latest: Arc<Mutex<String>>,
+    world: Arc<Mutex<Option<WorldConfig>>>,
+    interval: u64,
+    frames: u64,
+    pause_request: Option<bool>,
+}
+
+impl ApiServer {
+    pub fn start(port: u16, interval: u64) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let (sender, receiver) = mpsc::channel();
+        let shared = Shared {
+            commands: sender,
+            clients: Arc::new(Mutex::new(vec![])),
+            latest: Arc::new(Mutex::new("{}".to_string())),
+            world: Arc::new(Mutex::new(None)),
+        };
+        let server = ApiServer {
+            commands: Mutex::new(receiver),
+            clients: shared.clients.clone(),
+            latest: shared.latest.clone(),
+            world: shared.world.clone(),
+            interval: interval.max(1),
+            frames: 0,
+            pause_request: None,
+        };
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let shared = shared.clone();
+                        thread::spawn(move || handle_connection(stream, shared));
+                    },
+                    Err(e) => println!("Failed to accept API connection: {}", e),
+                }
+            }
+        });
+        println!("API listening on 127.0.0.1:{}", port);
+        Ok(server)
+    }
+
+    pub fn poll(&self) -> Vec<ApiCommand> {
+        match self.commands.lock() {
+            Ok(commands) => commands.try_iter().collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    pub fn due(&mut self) -> bool {
+        self.frames += 1;
+        self.frames % self.interval == 0
+    }
+
+    pub fn publish(&self, telemetry: &Telemetry) {
+        let message = match serde_json::to_string(telemetry) {
+            Ok(message) => message,
+            Err(e) => {
+                println!("Failed to encode telemetry: {}", e);
+                return
+            },
+        };
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain(|client| client.send(message.clone()).is_ok());
+        }
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = message;
+        }
+        if let Ok(mut world) = self.world.lock() {
+            *world = Some(telemetry.world.clone());
+        }
+    }
+
+    pub fn request_pause(&mut self, paused: bool) {
+        self.pause_request = Some(paused);
+    }
+
+    pub fn take_pause_request(&mut self) -> Option<bool> {
+        self.pause_request.take()
+    }
+}
+
+fn handle_connection(stream: TcpStream, shared: Shared) {
+    if let Err(e) = stream.set_read_timeout(Some(Duration::from_secs(1))) {
+        println!("Failed to configure API connection: {}", e);
+        return
+    }
+    let (head, body) = match read_head(&stream) {
+        Ok(request) => request,
+        Err(e) => {
+            println!("Failed to read API request: {}", e);
+            return
+        },
+    };
+    let upgrade = header(&head, "upgrade").map_or(false, |value| value.to_ascii_lowercase().contains("websocket"));
+    let result = if !header(&head, "origin").map_or(true, is_local_origin) {
+        refuse(stream, "403 Forbidden", "origin not allowed")
+    }
+    else if upgrade {
+        serve_websocket(stream, shared)
+    }
+    else {
+        serve_http(stream, &head, body, &shared)
+    };
+    if let Err(e) = result {
+        println!("API connection closed: {}", e);
+    }
+}
+
+// Peeks rather than reads so the websocket handshake still sees the full request.
+fn read_head(stream: &TcpStream) -> io::Result<(String, usize)> {
+    let mut buf = vec![0; MAX_REQUEST];
+    loop {
+        let n = stream.peek(&mut buf)?;
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"))
+        }
+        if let Some(end) = buf[..n].windows(4).position(|window| window == b"\r\n\r\n") {
+            return Ok((String::from_utf8_lossy(&buf[..end + 4]).to_string(), end + 4))
+        }
+        if n == buf.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request headers too large"))
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if key.eq_ignore_ascii_case(name) => Some(value.trim()),
+                _ => None,
+            }
+        })
+        .next()
+}
+
+// Browsers attach an Origin to cross-site requests, so anything but a local page (such as the
+// viewer served from "/") is some other website trying to drive the simulator.
+fn is_local_origin(origin: &str) -> bool {
+    let host = match origin.strip_prefix("http://") {
+        Some(host) => host,
+        None => return false,
+    };
+    let host = match host.rfind(':') {
+        Some(colon) if !host.ends_with(']') => &host[..colon],
+        _ => host,
+    };
+    host == "localhost" || host == "127.0.0.1" || host == "[::1]"
+}
+
+fn refuse(mut stream: TcpStream, status: &str, message: &str) -> io::Result<()> {
+    let response = error_json(message);
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           status, response.len(), response)?;
+    stream.flush()
+}
+
+fn serve_http(mut stream: TcpStream, head: &str, head_len: usize, shared: &Shared) -> io::Result<()> {
+    let length = header(head, "content-length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0usize)
+        .min(MAX_REQUEST);
+    let mut request = vec![0; head_len + length];
+    stream.read_exact(&mut request)?;
+    let body = &request[head_len..];
+
+    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+    let json = "application/json";
+    let method = request_line.next();
+    let path = request_line.next().and_then(|target| target.split('?').next());
+    let (status, content_type, response) = match (method, path) {
+        (Some("GET"), Some("/")) | (Some("GET"), Some("/viewer")) => ("200 OK", "text/html; charset=utf-8", VIEWER.to_string()),
+        (Some("GET"), Some("/telemetry")) => {
+            ("200 OK", json, shared.latest.lock().map(|latest| latest.clone()).unwrap_or_default())
+        },
+        // A JSON content type can't be sent cross-site without a preflight, which we never answer.
+        (Some("POST"), Some("/command")) if !header(head, "content-type").map_or(false, |value| value.starts_with(json)) => {
+            ("415 Unsupported Media Type", json, error_json("commands must be sent as application/json"))
+        },
+        (Some("POST"), Some("/command")) => match submit(body, shared) {
+            Ok(()) => ("200 OK", json, "{\"ok\":true}".to_string()),
+            Err(e) => ("400 Bad Request", json, error_json(&e)),
+        },
+        _ => ("404 Not Found", json, error_json("not found")),
+    };
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           status, content_type, response.len(), response)?;
+    stream.flush()
+}
+
+fn serve_websocket(stream: TcpStream, shared: Shared) -> io::Result<()> {
+    let mut socket: WebSocket<TcpStream> = tungstenite::accept(stream)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    let (sender, receiver) = mpsc::channel();
+    if let Ok(mut clients) = shared.clients.lock() {
+        clients.push(sender);
+    }
+
+    loop {
+        match socket.read_message() {
+            Ok(Message::Text(text)) => {
+                if let Err(e) = submit(text.as_bytes(), &shared) {
+                    socket.write_message(Message::Text(error_json(&e))).map_err(to_io)?;
+                }
+            },
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(ref e)) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {},
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(to_io(e)),
+        }
+        for message in receiver.try_iter() {
+            socket.write_message(Message::Text(message)).map_err(to_io)?;
+        }
+    }
+}
+
+fn submit(body: &[u8], shared: &Shared) -> Result<(), String> {
+    let command: ApiCommand = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    let world = shared.world.lock().ok().and_then(|world| world.clone());
+    command.check(world.as_ref())?;
+    shared.commands.send(command).map_err(|_| "simulator is shutting down".to_string())
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn to_io(e: tungstenite::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
@@ -0,0 +1,142 @@
+use amethyst::{
+    prelude::*,
+    core::{
+        math::RowVector3,
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        WriteStorage,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    api::{
+        ApiServer,
+        ApiCommand,
+        PendingCommands,
+        Telemetry,
+    },
+    metrics::Metrics,
+    flock::Flocks,
+    recording::BoidState,
+    resources::{
+        CurrentState,
+        Environment,
+        SimClock,
+        SpawnRequest,
+        SpawnRequests,
+        SpeciesList,
+        Weather,
+        WorldConfig,
+    },
+    scenario::{
+        Obstacle,
+        SimulationModel,
+        WanderConfig,
+    },
+};
+
+#[derive(SystemDesc)]
+pub struct ApiSystem;
+impl<'s> System<'s> for ApiSystem {
+    type SystemData = (WriteStorage<'s, Boid>,
+                       Option<Write<'s, ApiServer>>,
+                       Write<'s, PendingCommands>,
+                       Write<'s, SpawnRequests>,
+                       Write<'s, Environment>,
+                       Write<'s, Weather>,
+                       Write<'s, SpeciesList>,
+                       Write<'s, WanderConfig>,
+                       Write<'s, SimulationModel>,
+                       Read<'s, Metrics>,
+                       Read<'s, Flocks>,
+                       Read<'s, SimClock>,
+                       Read<'s, CurrentState>,
+                       Read<'s, WorldConfig>);
+
+    fn run(&mut self, (mut boids, mut server, mut pending, mut requests, mut environment, mut weather, mut species_list, mut wander_config, mut model, metrics, flocks, clock, state, world): Self::SystemData) {
+        let mut commands: Vec<ApiCommand> = pending.0.drain(..).collect();
+        if let Some(server) = &server {
+            commands.extend(server.poll());
+        }
+
+        for command in commands {
+            // Scripts queue commands directly, so they're checked here as well as at the socket.
+            if let Err(e) = command.check(Some(&world)) {
+                println!("Ignoring command: {}", e);
+                continue;
+            }
+            match command {
+                ApiCommand::Pause => if let Some(server) = server.as_mut() {
+                    server.request_pause(true);
+                },
+                ApiCommand::Resume => if let Some(server) = server.as_mut() {
+                    server.request_pause(false);
+                },
+                ApiCommand::SetParameter { parameter, species, value } => {
+                    let matches = |name: &str| species.as_ref().map_or(true, |species| species == name);
+                    for (idx, config) in species_list.0.iter_mut().enumerate() {
+                        if !matches(&config.name) {
+                            continue;
+                        }
+                        parameter.apply_genome(&mut config.genome, value);
+                        for boid in (&mut boids).join().filter(|boid| boid.get_species() == idx) {
+                            let mut genome = boid.get_genome().clone();
+                            parameter.apply_genome(&mut genome, value);
+                            boid.set_genome(genome);
+                        }
+                    }
+                    parameter.apply_wander(&mut wander_config, value);
+                    parameter.apply_model(&mut model, value);
+                },
+                ApiCommand::Spawn { position, velocity, species, count } => {
+                    let species = match &species {
+                        Some(name) => match species_list.0.iter().position(|config| &config.name == name) {
+                            Some(idx) => idx,
+                            None => {
+                                println!("Ignoring spawn of unknown species {}", name);
+                                continue;
+                            },
+                        },
+                        None => 0,
+                    };
+                    for _ in 0..count {
+                        requests.0.push(SpawnRequest {
+                            position: RowVector3::new(position.0, position.1, 0.0),
+                            velocity: RowVector3::new(velocity.0, velocity.1, 0.0),
+                            species,
+                        });
+                    }
+                },
+                ApiCommand::AddObstacle { position, radius } => environment.obstacles.push(Obstacle { position, radius }),
+                ApiCommand::ClearObstacles => environment.obstacles.clear(),
+                ApiCommand::SetWeather { weather: preset } => weather.set_weather(preset),
+            }
+        }
+
+        let server = match server.as_mut() {
+            Some(server) => server,
+            None => return,
+        };
+        if server.due() {
+            server.publish(&Telemetry {
+                tick: clock.tick,
+                time: clock.seconds,
+                paused: *state == CurrentState::Paused,
+                weather: weather.get_weather().clone(),
+                world: (*world).clone(),
+                species: species_list.0.iter().map(|species| species.name.clone()).collect(),
+                obstacles: environment.obstacles.clone(),
+                metrics: (*metrics).clone(),
+                flocks: flocks.flocks.clone(),
+                boids: boids.join().map(BoidState::new).collect(),
+            });
+        }
+    }
+}
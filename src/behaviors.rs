@@ -0,0 +1,373 @@
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use std::sync::Arc;
+use amethyst::core::math::RowVector3;
+use crate::{
+    script::Script,
+    steering::{
+        Neighbourhood,
+        SteeringPolicy,
+        nearest,
+    },
+    scenario::BoundaryMode,
+};
+
+pub trait SteeringBehavior: Send + Sync {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32>;
+}
+
+pub fn move_to_target(boid_pos: RowVector3<f32>, target: RowVector3<f32>) -> RowVector3<f32> {
+    (target - boid_pos) / 100.0
+}
+
+pub struct Cohesion;
+impl SteeringBehavior for Cohesion {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        if !hood.has_neighbours() {
+            return RowVector3::zeros()
+        }
+        ((hood.positions.row_sum() - hood.position)
+         .unscale(hood.positions.nrows() as f32 - 1.0) - hood.position)
+            .unscale(100.0) * hood.genome.cohesion
+    }
+}
+
+pub struct Separation;
+impl SteeringBehavior for Separation {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        let mut v_offset = RowVector3::zeros();
+        hood.positions.row_iter()
+            .enumerate()
+            .filter(|(i, x)| {
+            *i != hood.own_row && (x - hood.position).magnitude() < hood.genome.separation_radius
+        })
+        .for_each(|(_, x)| v_offset -= x - hood.position);
+
+        v_offset * hood.genome.separation
+    }
+}
+
+pub struct Alignment;
+impl SteeringBehavior for Alignment {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        if !hood.has_neighbours() {
+            return RowVector3::zeros()
+        }
+        ((hood.velocities.row_sum() - hood.velocity)
+         .unscale(hood.velocities.nrows() as f32 - 1.0) - hood.velocity)
+            .unscale(8.0) * hood.genome.alignment
+    }
+}
+
+pub struct Boundary;
+impl Boundary {
+    pub const MARGIN: f32 = 100.0;
+
+    pub fn limit_area(boid_pos: RowVector3<f32>, x_max: f32, y_max: f32, x_min: f32, y_min: f32) -> RowVector3<f32> {
+        let mut v_offset = RowVector3::<f32>::zeros();
+        if boid_pos[0] < x_min + Self::MARGIN {
+            v_offset[0] = 10.0;
+        }
+        else if boid_pos[0] > x_max - Self::MARGIN {
+            v_offset[0] = -10.0;
+        }
+
+        if boid_pos[1] < y_min + Self::MARGIN {
+            v_offset[1] = 10.0;
+        }
+        else if boid_pos[1] > y_max - Self::MARGIN {
+            v_offset[1] = -10.0;
+        }
+        v_offset
+    }
+}
+
+impl SteeringBehavior for Boundary {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        let world_dim = hood.world_dim;
+        match hood.boundary {
+            BoundaryMode::Steer => Self::limit_area(hood.position, world_dim.max_x, world_dim.max_y, world_dim.min_x, world_dim.min_y),
+            BoundaryMode::Wrap => RowVector3::zeros(),
+        }
+    }
+}
+
+pub struct Seek;
+impl SteeringBehavior for Seek {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        hood.environment.goals.iter()
+            .fold(RowVector3::zeros(), |acc, goal| acc + move_to_target(hood.position, goal.get_position()) * goal.weight)
+    }
+}
+
+pub struct Flee;
+impl SteeringBehavior for Flee {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        let radius = hood.genome.perception_radius;
+        let mut v_offset = RowVector3::zeros();
+        for threat in hood.threats {
+            let offset = hood.position - threat;
+            let dist = offset.magnitude();
+            if dist > 0.0 && dist < radius {
+                v_offset += offset.unscale(dist) * (radius - dist);
+            }
+        }
+        v_offset
+    }
+}
+
+pub struct Chase;
+impl SteeringBehavior for Chase {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        match nearest(hood.position, hood.prey) {
+            Some(target) if target != hood.position => (target - hood.position).normalize() * hood.genome.max_speed - hood.velocity,
+            _ => RowVector3::zeros(),
+        }
+    }
+}
+
+pub struct Forage;
+impl SteeringBehavior for Forage {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        hood.food.map_or(RowVector3::zeros(), |food| move_to_target(hood.position, food) * hood.energy_config.forage_weight)
+    }
+}
+
+pub struct Wander;
+impl SteeringBehavior for Wander {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        let angle = match hood.wander {
+            Some(angle) => angle,
+            None => return RowVector3::zeros(),
+        };
+        let heading = if hood.velocity.magnitude_squared() > 0.0 {
+            hood.velocity[1].atan2(hood.velocity[0])
+        }
+        else {
+            0.0
+        };
+        let config = hood.wander_config;
+        let center = RowVector3::new(heading.cos(), heading.sin(), 0.0) * config.distance;
+        let displacement = RowVector3::new((heading + angle).cos(), (heading + angle).sin(), 0.0) * config.radius;
+        center + displacement
+    }
+}
+
+pub struct ObstacleAvoidance;
+impl SteeringBehavior for ObstacleAvoidance {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        let mut v_offset = RowVector3::zeros();
+        for obstacle in &hood.environment.obstacles {
+            let offset = hood.position - obstacle.get_position();
+            let dist = offset.magnitude();
+            let reach = obstacle.radius + 50.0;
+            if dist > 0.0 && dist < reach {
+                v_offset += offset.unscale(dist) * (reach - dist);
+            }
+        }
+        v_offset
+    }
+}
+
+pub struct ScriptBehavior {
+    script: Option<Arc<Script>>,
+    function: String,
+}
+impl SteeringBehavior for ScriptBehavior {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        match &self.script {
+            Some(script) => script.steer(&self.function, hood),
+            None => RowVector3::zeros(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BehaviorKind {
+    Cohesion,
+    Separation,
+    Alignment,
+    Boundary,
+    Seek,
+    Flee,
+    Chase,
+    Forage,
+    ObstacleAvoidance,
+    Wander,
+    Script(String),
+}
+
+impl BehaviorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BehaviorKind::Cohesion => "cohesion",
+            BehaviorKind::Separation => "separation",
+            BehaviorKind::Alignment => "alignment",
+            BehaviorKind::Boundary => "boundary",
+            BehaviorKind::Seek => "seek",
+            BehaviorKind::Flee => "flee",
+            BehaviorKind::Chase => "chase",
+            BehaviorKind::Forage => "forage",
+            BehaviorKind::ObstacleAvoidance => "obstacle_avoidance",
+            BehaviorKind::Wander => "wander",
+            BehaviorKind::Script(_) => "script",
+        }
+    }
+
+    pub fn build(&self, script: Option<&Arc<Script>>) -> Box<dyn SteeringBehavior> {
+        match self {
+            BehaviorKind::Cohesion => Box::new(Cohesion),
+            BehaviorKind::Separation => Box::new(Separation),
+            BehaviorKind::Alignment => Box::new(Alignment),
+            BehaviorKind::Boundary => Box::new(Boundary),
+            BehaviorKind::Seek => Box::new(Seek),
+            BehaviorKind::Flee => Box::new(Flee),
+            BehaviorKind::Chase => Box::new(Chase),
+            BehaviorKind::Forage => Box::new(Forage),
+            BehaviorKind::ObstacleAvoidance => Box::new(ObstacleAvoidance),
+            BehaviorKind::Wander => Box::new(Wander),
+            BehaviorKind::Script(function) => {
+                if script.is_none() {
+                    println!("Script behaviour {} needs a scenario script, ignoring it", function);
+                }
+                Box::new(ScriptBehavior {
+                    script: script.cloned(),
+                    function: function.clone(),
+                })
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightedBehavior {
+    pub behavior: BehaviorKind,
+    #[serde(default = "WeightedBehavior::default_weight")]
+    pub weight: f32,
+}
+
+impl WeightedBehavior {
+    fn default_weight() -> f32 {
+        1.0
+    }
+
+    fn new(behavior: BehaviorKind) -> Self {
+        WeightedBehavior {
+            behavior,
+            weight: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    pub behaviors: Vec<WeightedBehavior>,
+    #[serde(default)]
+    pub max_force: Option<f32>,
+    #[serde(default)]
+    pub prioritized: bool,
+}
+
+impl PipelineConfig {
+    pub fn reynolds(predator: bool) -> Self {
+        let behaviors = if predator {
+            vec![
+                BehaviorKind::Separation,
+                BehaviorKind::Boundary,
+                BehaviorKind::Seek,
+                BehaviorKind::ObstacleAvoidance,
+                BehaviorKind::Forage,
+                BehaviorKind::Chase,
+                BehaviorKind::Wander,
+            ]
+        }
+        else {
+            vec![
+                BehaviorKind::Cohesion,
+                BehaviorKind::Separation,
+                BehaviorKind::Alignment,
+                BehaviorKind::Boundary,
+                BehaviorKind::Seek,
+                BehaviorKind::ObstacleAvoidance,
+                BehaviorKind::Forage,
+                BehaviorKind::Flee,
+                BehaviorKind::Wander,
+            ]
+        };
+        PipelineConfig {
+            behaviors: behaviors.into_iter().map(WeightedBehavior::new).collect(),
+            max_force: None,
+            prioritized: false,
+        }
+    }
+
+    pub fn build(&self, script: Option<&Arc<Script>>) -> BehaviorPipeline {
+        BehaviorPipeline {
+            behaviors: self.behaviors.iter()
+                .map(|weighted| (weighted.behavior.name(), weighted.behavior.build(script), weighted.weight))
+                .collect(),
+            max_force: self.max_force,
+            prioritized: self.prioritized,
+        }
+    }
+}
+
+pub struct BehaviorPipeline {
+    behaviors: Vec<(&'static str, Box<dyn SteeringBehavior>, f32)>,
+    max_force: Option<f32>,
+    prioritized: bool,
+}
+
+impl BehaviorPipeline {
+    fn truncate(force: RowVector3<f32>, max_force: f32) -> RowVector3<f32> {
+        let magnitude = force.magnitude();
+        if magnitude > max_force {
+            force * (max_force / magnitude)
+        }
+        else {
+            force
+        }
+    }
+
+    fn combine(&self, hood: &Neighbourhood, mut parts: Option<&mut Vec<(&'static str, RowVector3<f32>)>>) -> RowVector3<f32> {
+        let mut total = RowVector3::zeros();
+        // Prioritized mode hands out the force budget by magnitude, so opposing
+        // forces still use it up instead of cancelling and letting later ones overshoot.
+        let mut used = 0.0;
+        for (name, behavior, weight) in &self.behaviors {
+            let force = behavior.steer(hood) * *weight;
+            let force = match self.max_force {
+                Some(max_force) if self.prioritized => {
+                    let remaining = max_force - used;
+                    if remaining <= 0.0 {
+                        break;
+                    }
+                    let force = Self::truncate(force, remaining);
+                    used += force.magnitude();
+                    force
+                },
+                _ => force,
+            };
+            if let Some(parts) = parts.as_mut() {
+                parts.push((name, force));
+            }
+            total += force;
+        }
+        match self.max_force {
+            Some(max_force) => Self::truncate(total, max_force),
+            None => total,
+        }
+    }
+}
+
+impl SteeringPolicy for BehaviorPipeline {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        self.combine(hood, None)
+    }
+
+    fn steer_explained(&self, hood: &Neighbourhood, parts: &mut Vec<(&'static str, RowVector3<f32>)>) -> RowVector3<f32> {
+        self.combine(hood, Some(parts))
+    }
+}
@@ -2,23 +2,36 @@ use amethyst::{
     ecs::prelude::{
         Component,
         VecStorage,
+        Entity,
     },
     core::math::RowVector3,
 };
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use crate::genome::Genome;
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct Boid {
     pub id: usize,
+    #[serde(skip)]
+    entity: Option<Entity>,
     position: RowVector3<f32>,
     velocity: RowVector3<f32>,
+    species: usize,
+    genome: Genome,
 }
 
 impl Boid {
     pub fn new(position: RowVector3<f32>, id: usize) -> Self {
         Boid {
             id,
+            entity: None,
             position,
             velocity: RowVector3::zeros(),
+            species: 0,
+            genome: Genome::default(),
         }
     }
 
@@ -26,6 +39,10 @@ impl Boid {
         self.id = id;
     }
 
+    pub fn set_entity(&mut self, entity: Entity) {
+        self.entity = Some(entity);
+    }
+
     pub fn set_position(&mut self, position: RowVector3<f32>) {
         self.position = position;
     }
@@ -34,6 +51,18 @@ impl Boid {
         self.velocity = velocity;
     }
     
+    pub fn set_species(&mut self, species: usize) {
+        self.species = species;
+    }
+    
+    pub fn set_genome(&mut self, genome: Genome) {
+        self.genome = genome;
+    }
+
+    pub fn get_entity(&self) -> Option<Entity> {
+        self.entity
+    }
+
     pub fn get_position(&self) -> RowVector3<f32> {
         self.position
     }
@@ -41,6 +70,14 @@ impl Boid {
     pub fn get_velocity(&self) -> RowVector3<f32> {
         self.velocity
     }
+
+    pub fn get_species(&self) -> usize {
+        self.species
+    }
+
+    pub fn get_genome(&self) -> &Genome {
+        &self.genome
+    }
 }
 
 impl Component for Boid {
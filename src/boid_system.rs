@@ -15,14 +15,32 @@ use amethyst::{
         Read,
         Write,
         WriteStorage,
+        ReadStorage,
         Entities,
+        Join,
     },
 };
 use crate::{
     boid::Boid,
+    energy::Energy,
+    food::Food,
+    wander::Wander,
     resources::{
         WorldDimensions,
         BoidInfo,   
+        Environment,
+        SpeciesList,
+        Selection,
+    },
+    steering::{
+        Neighbourhood,
+        SteeringPolicies,
+    },
+    scenario::{
+        BoundaryMode,
+        EnergyConfig,
+        WanderConfig,
+        SimulationModel,
     },
     types::MatrixNx3f,
     quadtree::QuadTree,
@@ -31,54 +49,49 @@ use crate::{
 #[derive(SystemDesc)]
 pub struct BoidSystem;
 impl BoidSystem {
-    fn cohesion(boid_pos: RowVector3<f32>, positions: &MatrixNx3f) -> RowVector3<f32>{
-        ((positions.row_sum() - boid_pos)
-         .unscale(positions.nrows() as f32 - 1.0) - boid_pos)
-            .unscale(100.0)
+    fn visible_food<'a>(boid_pos: RowVector3<f32>, energy: Option<&Energy>, food: impl Iterator<Item = &'a Food>, config: &EnergyConfig) -> Option<RowVector3<f32>> {
+        let hungry = energy.map_or(false, |energy| energy.fraction() < config.hunger_threshold);
+        if !hungry {
+            return None
+        }
+
+        food.filter(|patch| patch.get_amount() > 0.0)
+            .map(|patch| patch.get_position())
+            .filter(|pos| (pos - boid_pos).magnitude() < config.perception)
+            .min_by(|a, b| (a - boid_pos).magnitude_squared()
+                    .partial_cmp(&(b - boid_pos).magnitude_squared())
+                    .unwrap_or(std::cmp::Ordering::Equal))
     }
 
-    fn separation(boid_pos: RowVector3<f32>, positions: &MatrixNx3f, boid_num: usize) -> RowVector3<f32> {
-        let mut v_offset = RowVector3::zeros();
-        positions.row_iter()
-            .enumerate()
-            .filter(|(i, x)| {
-            *i != boid_num && (x - boid_pos).magnitude_squared() < 1000.0
-        })
-        .for_each(|(_, x)| v_offset -= x - boid_pos);
+    fn neighbourhood(boid_num: usize, positions: &MatrixNx3f, velocities: &MatrixNx3f, species: &[usize], radius: f32) -> (MatrixNx3f, MatrixNx3f, usize, Vec<usize>) {
+        let boid_pos = positions.row(boid_num);
+        let near: Vec<usize> = (0..positions.nrows())
+            .filter(|j| species[*j] == species[boid_num])
+            .filter(|j| *j == boid_num || (positions.row(*j) - boid_pos).magnitude() <= radius)
+            .collect();
 
-        v_offset
-    }
-    
-    fn alignment(boid_vel: RowVector3<f32>, velocities: &MatrixNx3f) -> RowVector3<f32>{
-        ((velocities.row_sum() - boid_vel)
-         .unscale(velocities.nrows() as f32 - 1.0) - boid_vel)
-            .unscale(8.0)
+        let mut near_pos = MatrixNx3f::zeros(near.len());
+        let mut near_vel = MatrixNx3f::zeros(near.len());
+        let mut own_row = 0;
+        for (row, j) in near.iter().enumerate() {
+            near_pos.set_row(row, &positions.row(*j));
+            near_vel.set_row(row, &velocities.row(*j));
+            if *j == boid_num {
+                own_row = row;
+            }
+        }
+        (near_pos, near_vel, own_row, near)
     }
 
-    fn move_to_target(boid_vel: RowVector3<f32>) -> RowVector3<f32> {
-        (RowVector3::new(500.0, 500.0, 0.0) - boid_vel) / 100.0
+    pub fn wrap_position(boid_pos: &mut RowVector3<f32>, world_dim: &WorldDimensions) {
+        let width = world_dim.max_x - world_dim.min_x;
+        let height = world_dim.max_y - world_dim.min_y;
+        boid_pos[0] = world_dim.min_x + (boid_pos[0] - world_dim.min_x).rem_euclid(width);
+        boid_pos[1] = world_dim.min_y + (boid_pos[1] - world_dim.min_y).rem_euclid(height);
     }
     
-    fn limit_area(boid_pos: RowVector3<f32>, x_max: f32, y_max: f32, x_min: f32, y_min: f32) -> RowVector3<f32> {
-        let mut v_offset = RowVector3::<f32>::zeros();
-        if boid_pos[0] < x_min + 100.0{
-            v_offset[0] = 10.0;
-        }
-        else if boid_pos[0] > x_max - 100.0 {
-            v_offset[0] = -10.0;
-        }
-        
-        if boid_pos[1] < y_min + 100.0 {
-            v_offset[1] = 10.0;
-        }
-        else if boid_pos[1] > y_max - 100.0 {
-            v_offset[1] = -10.0;
-        }
-        v_offset
-    }
-    
-    fn limit_velocity(boid_vel: RowVector3<f32>, velocities: &mut MatrixNx3f, boid_num: usize) {
-        let vlim = 2500.0;
+    fn limit_velocity(boid_vel: RowVector3<f32>, velocities: &mut MatrixNx3f, boid_num: usize, max_speed: f32) {
+        let vlim = max_speed * max_speed;
         let vel = boid_vel.magnitude_squared();
         if vel > vlim {
             velocities.set_row(boid_num, &(boid_vel.unscale(vel) * vlim));
@@ -94,10 +107,25 @@ impl<'s> System<'s> for BoidSystem {
                        WriteStorage<'s, Transform>,
                        WriteStorage<'s, Boid>,
                        Read<'s, WorldDimensions>,
+                       Read<'s, Environment>,
+                       Read<'s, BoundaryMode>,
+                       ReadStorage<'s, Energy>,
+                       ReadStorage<'s, Food>,
+                       WriteStorage<'s, Wander>,
+                       Read<'s, EnergyConfig>,
+                       Read<'s, WanderConfig>,
+                       Read<'s, SpeciesList>,
+                       Read<'s, SteeringPolicies>,
+                       Read<'s, SimulationModel>,
+                       Write<'s, Selection>,
                        Read<'s, Time>,
                        Entities<'s>);
     
-    fn run(&mut self, (mut boid_info, mut locals, mut boids, world_dim, time, entities): Self::SystemData) {
+    fn run(&mut self, (mut boid_info, mut locals, mut boids, world_dim, environment, boundary, energies, food, mut wanders, energy_config, wander_config, species_list, policies, model, mut selection, time, entities): Self::SystemData) {
+        if let SimulationModel::Vicsek(_) = *model {
+            return
+        }
+        let has_predators = species_list.has_predators();
         //println!("Old pos matrix: {}", boid_info.positions);
         let mut qt = boid_info.qt.clone();
         
@@ -107,44 +135,94 @@ impl<'s> System<'s> for BoidSystem {
             let nrows = region.len();
             let mut velocities = MatrixNx3f::zeros(nrows);
             let mut positions = MatrixNx3f::zeros(nrows);
-            let mut ids = vec![];
+            let mut boid_entities = vec![];
+            let mut species = vec![];
             
             for (i, boid) in region.iter().enumerate() {
                 let pos = boid.get_position();
                 let vel = boid.get_velocity();
                 velocities.set_row(i, &vel);
                 positions.set_row(i, &pos);
-                ids.push(boid.id);
+                boid_entities.push(boid.get_entity());
+                species.push(boid.get_species());
             }
             
-            for (i, id) in ids.iter().enumerate() {
-                let entity = entities.entity(*id as u32);
-                let boid = boids.get_mut(entity).unwrap();
-                let transform = locals.get_mut(entity).unwrap();
+            for (i, entity) in boid_entities.iter().enumerate() {
+                let entity = match entity {
+                    Some(entity) if entities.is_alive(*entity) => *entity,
+                    _ => continue,
+                };
+                let (boid, transform) = match (boids.get_mut(entity), locals.get_mut(entity)) {
+                    (Some(boid), Some(transform)) => (boid, transform),
+                    _ => continue,
+                };
                 
                 let mut boid_pos: RowVector3<f32> = positions.row(i).into();
                 let boid_vel: RowVector3<f32> = velocities.row(i).into();
+                let genome = boid.get_genome().clone();
+                let is_predator = species_list.is_predator(species[i]);
 
-                let v1 = Self::cohesion(boid_pos, &positions);
-                let v2 = Self::separation(boid_pos, &positions, i);
-                let v3 = Self::alignment(boid_vel, &velocities);
-                let v4 = Self::limit_area(boid_pos, world_dim.max_x, world_dim.max_y, world_dim.min_x, world_dim.min_y);
-                let v5 = Self::move_to_target(boid_pos);
-
-                if nrows == 1 {
-                    Self::limit_velocity(boid_vel + v2 + v4, &mut velocities, i); 
+                let (near_pos, near_vel, own_row, near) = Self::neighbourhood(i, &positions, &velocities, &species, genome.perception_radius);
+                let (threats, prey) = if has_predators {
+                    let r = genome.perception_radius;
+                    let (threats, prey): (Vec<Boid>, Vec<Boid>) = qt.search_range(boid_pos[0] - r..boid_pos[0] + r, boid_pos[1] - r..boid_pos[1] + r)
+                        .into_iter()
+                        .partition(|other| species_list.is_predator(other.get_species()));
+                    (threats.iter().map(Boid::get_position).collect(), prey.iter().map(Boid::get_position).collect())
                 }
                 else {
-                    Self::limit_velocity(boid_vel + v1 + v2 + v3 + v4, &mut velocities, i); 
+                    (vec![], vec![])
+                };
+
+                let wander = wanders.get_mut(entity).map(|wander| {
+                    wander.jitter(wander_config.jitter * time.delta_seconds());
+                    wander.get_angle()
+                });
+
+                let hood = Neighbourhood {
+                    position: boid_pos,
+                    velocity: boid_vel,
+                    genome: &genome,
+                    is_predator,
+                    positions: &near_pos,
+                    velocities: &near_vel,
+                    own_row,
+                    threats: &threats,
+                    prey: &prey,
+                    food: Self::visible_food(boid_pos, energies.get(entity), (&food).join(), &energy_config),
+                    energy_config: &energy_config,
+                    wander,
+                    wander_config: &wander_config,
+                    environment: &environment,
+                    world_dim: &world_dim,
+                    boundary: &boundary,
+                };
+                let policy = policies.get(species[i], is_predator);
+                let steering = if selection.entity == Some(entity) {
+                    selection.neighbours = near.iter()
+                        .filter(|j| **j != i)
+                        .filter_map(|j| boid_entities[*j])
+                        .collect();
+                    selection.forces.clear();
+                    let steering = policy.steer_explained(&hood, &mut selection.forces);
+                    selection.forces.push(("total", steering));
+                    steering
                 }
+                else {
+                    policy.steer(&hood)
+                };
+                Self::limit_velocity(boid_vel + steering, &mut velocities, i, genome.max_speed);
 
                 boid_pos += velocities.row(i) * time.delta_seconds();
+                if *boundary == BoundaryMode::Wrap {
+                    Self::wrap_position(&mut boid_pos, &world_dim);
+                }
                 positions.set_row(i, &boid_pos);
 
                 let angle = velocities.row(i)[1].atan2(velocities.row(i)[0]) - std::f32::consts::PI / 2.0;
 
                 transform.set_rotation_2d(angle);
-                transform.prepend_translation(velocities.row(i).transpose().scale(time.delta_seconds()));
+                transform.set_translation(boid_pos.transpose());
 
                 boid.set_velocity(velocities.row(i).into());
                 boid.set_position(boid_pos);
@@ -9,6 +9,7 @@ use amethyst::{
         transform::Transform,
         SystemDesc,
         timing::Time,
+        math::RowVector3,
     },
     ecs::prelude::{
         System,
@@ -16,37 +17,164 @@ use amethyst::{
         WriteStorage,
         ReadStorage,
         Read,
+        Write,
         Join,
     },
     renderer::{
         Camera
     },
+    window::ScreenDimensions,
+};
+use crate::{
+    boid::Boid,
+    flock::{
+        Flock,
+        Flocks,
+    },
+    resources::{
+        CameraMode,
+        CameraView,
+        Selection,
+    },
+    simulator::Simulator,
 };
 
+const SMOOTHING: f32 = 3.0;
+const FIT_MARGIN: f32 = 1.2;
+const PAN_SPEED: f32 = 300.0;
+const ZOOM_SPEED: f32 = 1.5;
+const WHEEL_STEP: f32 = 1.1;
+
 #[derive(SystemDesc)]
 pub struct CameraSystem;
+impl CameraSystem {
+    fn centroid(positions: impl Iterator<Item = RowVector3<f32>>) -> Option<RowVector3<f32>> {
+        let (sum, count) = positions.fold((RowVector3::zeros(), 0), |(sum, count), pos| (sum + pos, count + 1));
+        if count == 0 {
+            None
+        }
+        else {
+            Some(sum / count as f32)
+        }
+    }
+
+    fn fit_scale(min: RowVector3<f32>, max: RowVector3<f32>, screen: &ScreenDimensions) -> f32 {
+        let extent = (max - min) * FIT_MARGIN;
+        (extent[0] / screen.width()).max(extent[1] / screen.height())
+    }
+}
+
 impl<'s> System<'s> for CameraSystem {
-    type SystemData = (ReadStorage<'s, Camera>,
+    type SystemData = (WriteStorage<'s, Camera>,
                        WriteStorage<'s, Transform>,
+                       ReadStorage<'s, Boid>,
+                       ReadStorage<'s, Flock>,
                        Read<'s, InputHandler<StringBindings>>,
-                       Read<'s, Time>);
-    fn run(&mut self, (cameras, mut transforms, input, time): Self::SystemData) {
-        for (camera, transform) in (&cameras, &mut transforms).join() {
-            let x_axis = input.axis_value("horizontal");
-            if let Some(mv_amnt) = x_axis {
-                transform.prepend_translation_x((2.0 + time.delta_seconds()) * mv_amnt as f32);
-            }
+                       Read<'s, Time>,
+                       Read<'s, Selection>,
+                       Read<'s, Flocks>,
+                       Write<'s, CameraMode>,
+                       Write<'s, CameraView>,
+                       Option<Read<'s, ScreenDimensions>>);
+
+    fn run(&mut self, (mut cameras, mut transforms, boids, flock_ids, input, time, selection, flocks, mut mode, mut view, screen): Self::SystemData) {
+        let screen = match screen {
+            Some(screen) => screen,
+            None => return,
+        };
+        let is_down = |action: &str| input.action_is_down(action).unwrap_or(false);
+        let x_axis = input.axis_value("horizontal").unwrap_or(0.0);
+        let y_axis = input.axis_value("vertical").unwrap_or(0.0);
+        let z_axis = input.axis_value("zoom").unwrap_or(0.0);
+        let wheel = input.axis_value("wheel_zoom").unwrap_or(0.0);
+        let mouse = input.mouse_position();
+        let dragged = match (is_down("drag_pan"), mouse) {
+            (true, Some(mouse)) => view.drag.replace(mouse).map(|last| (mouse.0 - last.0, mouse.1 - last.1)),
+            _ => {
+                view.drag = None;
+                None
+            },
+        };
+
+        let zooming = z_axis != 0.0 || wheel != 0.0;
+        if is_down("camera_free") || x_axis != 0.0 || y_axis != 0.0 || dragged.is_some() || (zooming && *mode == CameraMode::FitAll) {
+            *mode = CameraMode::Free;
+        }
+        else if is_down("camera_follow") {
+            *mode = CameraMode::Follow;
+        }
+        else if is_down("camera_flock") {
+            *mode = CameraMode::Flock;
+        }
+        else if is_down("camera_fit") {
+            *mode = CameraMode::FitAll;
+        }
+
+        // (x, y) to look at and, for fit mode, the scale that shows every boid.
+        let target = match *mode {
+            CameraMode::Free => None,
+            CameraMode::Follow => selection.entity
+                .and_then(|entity| boids.get(entity))
+                .map(|boid| (boid.get_position(), None)),
+            CameraMode::Flock => {
+                let largest = flocks.flocks.iter().max_by_key(|flock| flock.size).map(|flock| flock.id);
+                match largest {
+                    Some(id) => Self::centroid((&boids, &flock_ids).join()
+                                               .filter(|(_, flock)| flock.get_id() == id)
+                                               .map(|(boid, _)| boid.get_position())),
+                    None => Self::centroid(boids.join().map(Boid::get_position)),
+                }.map(|centroid| (centroid, None))
+            },
+            CameraMode::FitAll => {
+                let bounds = boids.join().map(Boid::get_position).fold(None, |bounds: Option<(RowVector3<f32>, RowVector3<f32>)>, pos| {
+                    Some(match bounds {
+                        Some((min, max)) => (min.zip_map(&pos, f32::min), max.zip_map(&pos, f32::max)),
+                        None => (pos, pos),
+                    })
+                });
+                bounds.map(|(min, max)| ((min + max) / 2.0, Some(Self::fit_scale(min, max, &screen))))
+            },
+        };
+
+        // Real time so the camera still moves and eases while the simulation is paused or slowed down.
+        let dt = time.delta_real_seconds();
+        let blend = 1.0 - (-SMOOTHING * dt).exp();
+        let old_scale = view.get_scale();
+        let scale = match target {
+            Some((_, Some(fit))) => old_scale + (fit - old_scale) * blend,
+            _ => old_scale * (ZOOM_SPEED * z_axis * dt).exp() * WHEEL_STEP.powf(-wheel),
+        };
+        view.set_scale(scale);
+        let scale = view.get_scale();
 
-            let y_axis = input.axis_value("vertical");
-            if let Some(mv_amnt) = y_axis {
-                transform.prepend_translation_y((2.0 + time.delta_seconds()) * mv_amnt as f32);
+        for (camera, transform) in (&mut cameras, &mut transforms).join() {
+            // Keep the world point under the cursor in place while zooming with the wheel.
+            if wheel != 0.0 {
+                if let Some(cursor) = mouse.and_then(|mouse| Simulator::screen_to_world(camera, transform, &screen, mouse)) {
+                    let current = *transform.translation();
+                    let ratio = scale / old_scale;
+                    transform.set_translation_x(cursor[0] + (current.x - cursor[0]) * ratio);
+                    transform.set_translation_y(cursor[1] + (current.y - cursor[1]) * ratio);
+                }
             }
+            *camera = Simulator::orthographic_camera(screen.width(), screen.height(), scale);
 
-            let z_axis = input.axis_value("zoom");
-            if let Some(mv_amnt) = z_axis {
-                transform.prepend_translation_z((1.0 + time.delta_seconds()) * mv_amnt as f32);
+            match target {
+                Some((focus, _)) => {
+                    let current = *transform.translation();
+                    transform.set_translation_x(current.x + (focus[0] - current.x) * blend);
+                    transform.set_translation_y(current.y + (focus[1] - current.y) * blend);
+                },
+                None => {
+                    // Pan speed is in screen pixels so it feels the same at every zoom level.
+                    transform.prepend_translation_x(PAN_SPEED * scale * dt * x_axis);
+                    transform.prepend_translation_y(PAN_SPEED * scale * dt * y_axis);
+                    if let Some((dx, dy)) = dragged {
+                        transform.prepend_translation_x(-dx * scale);
+                        transform.prepend_translation_y(dy * scale);
+                    }
+                },
             }
-            println!("{}", transform.translation())
         }
     }
 }
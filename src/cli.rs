@@ -0,0 +1,63 @@
+use std::{
+    collections::HashSet,
+    env,
+    path::PathBuf,
+    str::FromStr,
+};
+
+#[derive(Default)]
+pub struct Args {
+    pub scenario: Option<PathBuf>,
+    pub evolve: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub load: Option<PathBuf>,
+    pub export: Option<PathBuf>,
+    pub export_interval: u64,
+    pub export_ids: Option<HashSet<usize>>,
+    pub parquet: Option<PathBuf>,
+    pub parquet_batch: u64,
+    pub sweep: Option<PathBuf>,
+    pub headless: Option<f32>,
+    pub time_step: Option<f32>,
+    pub summary: Option<PathBuf>,
+    pub api: Option<u16>,
+    pub api_interval: u64,
+}
+
+impl Args {
+    fn value<T: FromStr>(iter: &mut impl Iterator<Item = String>, flag: &str, expected: &str) -> Result<T, String> {
+        iter.next()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("{} expects {}", flag, expected))
+    }
+
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--scenario" | "-s" => args.scenario = iter.next().map(PathBuf::from),
+                "--evolve" => args.evolve = iter.next().map(PathBuf::from),
+                "--record" => args.record = iter.next().map(PathBuf::from),
+                "--replay" => args.replay = iter.next().map(PathBuf::from),
+                "--load" => args.load = iter.next().map(PathBuf::from),
+                "--export" => args.export = iter.next().map(PathBuf::from),
+                "--export-interval" => args.export_interval = Self::value(&mut iter, &arg, "a number of ticks")?,
+                "--parquet" => args.parquet = iter.next().map(PathBuf::from),
+                "--parquet-batch" => args.parquet_batch = Self::value(&mut iter, &arg, "a number of ticks")?,
+                "--api" => args.api = Some(Self::value(&mut iter, &arg, "a port number")?),
+                "--api-interval" => args.api_interval = Self::value(&mut iter, &arg, "a number of frames")?,
+                "--sweep" => args.sweep = iter.next().map(PathBuf::from),
+                "--headless" => args.headless = Some(Self::value(&mut iter, &arg, "a duration in seconds")?),
+                "--time-step" => args.time_step = Some(Self::value(&mut iter, &arg, "a time step in seconds")?),
+                "--summary" => args.summary = iter.next().map(PathBuf::from),
+                "--export-ids" => args.export_ids = Some(iter.next()
+                    .and_then(|ids| ids.split(',').map(|id| id.trim().parse().ok()).collect())
+                    .ok_or_else(|| "--export-ids expects a comma separated list of boid ids".to_string())?),
+                _ => eprintln!("Ignoring unknown argument {}", arg),
+            }
+        }
+        Ok(args)
+    }
+}
@@ -0,0 +1,26 @@
+use amethyst::{
+    prelude::*,
+    core::{
+        timing::Time,
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+    },
+};
+use crate::resources::SimClock;
+
+#[derive(SystemDesc)]
+pub struct ClockSystem;
+impl<'s> System<'s> for ClockSystem {
+    type SystemData = (Write<'s, SimClock>,
+                       Read<'s, Time>);
+
+    fn run(&mut self, (mut clock, time): Self::SystemData) {
+        clock.advance(time.delta_seconds());
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use amethyst::{
+    prelude::*,
+    core::SystemDesc,
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        WriteStorage,
+        Entities,
+        Entity,
+        Join,
+    },
+    renderer::resources::Tint,
+};
+use crate::{
+    boid::Boid,
+    flock::{
+        Flock,
+        Flocks,
+    },
+    resources::{
+        BoidInfo,
+        SimClock,
+    },
+    scenario::ClusterConfig,
+};
+
+#[derive(SystemDesc)]
+pub struct ClusterSystem;
+impl ClusterSystem {
+    fn find(parents: &mut Vec<usize>, i: usize) -> usize {
+        let mut root = i;
+        while parents[root] != root {
+            root = parents[root];
+        }
+        let mut node = i;
+        while parents[node] != root {
+            let next = parents[node];
+            parents[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    fn union(parents: &mut Vec<usize>, a: usize, b: usize) {
+        let (a, b) = (Self::find(parents, a), Self::find(parents, b));
+        if a != b {
+            parents[a.max(b)] = a.min(b);
+        }
+    }
+}
+
+impl<'s> System<'s> for ClusterSystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       WriteStorage<'s, Flock>,
+                       WriteStorage<'s, Tint>,
+                       Write<'s, BoidInfo>,
+                       Write<'s, Flocks>,
+                       Read<'s, ClusterConfig>,
+                       Read<'s, SimClock>,
+                       Entities<'s>);
+
+    fn run(&mut self, (boids, mut flock_ids, mut tints, mut boid_info, mut flocks, config, clock, entities): Self::SystemData) {
+        if !config.enabled {
+            return
+        }
+        flocks.ticks += 1;
+        if flocks.ticks % config.interval.max(1) != 0 {
+            return
+        }
+
+        let members: Vec<(Entity, &Boid)> = (&entities, &boids).join().collect();
+        let index: HashMap<Entity, usize> = members.iter().enumerate().map(|(i, (entity, _))| (*entity, i)).collect();
+        let mut parents: Vec<usize> = (0..members.len()).collect();
+        let r = config.link_radius;
+        for (i, (_, boid)) in members.iter().enumerate() {
+            let pos = boid.get_position();
+            for other in boid_info.qt.search_range(pos[0] - r..pos[0] + r, pos[1] - r..pos[1] + r) {
+                if other.get_species() != boid.get_species() || (other.get_position() - pos).magnitude() > r {
+                    continue;
+                }
+                if let Some(j) = other.get_entity().and_then(|entity| index.get(&entity)) {
+                    Self::union(&mut parents, i, *j);
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for i in 0..members.len() {
+            let root = Self::find(&mut parents, i);
+            groups.entry(root).or_insert_with(Vec::new).push(i);
+        }
+        let mut roots: Vec<usize> = groups.keys().cloned().filter(|root| groups[root].len() >= config.min_size).collect();
+        roots.sort();
+        let clusters: Vec<(usize, Vec<(Entity, Option<usize>)>)> = roots.iter()
+            .map(|root| (members[*root].1.get_species(), groups[root].iter()
+                         .map(|i| (members[*i].0, flock_ids.get(members[*i].0).map(Flock::get_id)))
+                         .collect()))
+            .collect();
+
+        let ids = flocks.track(&clusters, clock.seconds);
+        for (entity, _) in &members {
+            flock_ids.remove(*entity);
+            if config.colour {
+                tints.remove(*entity);
+            }
+        }
+        for ((_, cluster), id) in clusters.iter().zip(ids) {
+            for (entity, _) in cluster {
+                flock_ids.insert(*entity, Flock::new(id)).ok();
+                if config.colour {
+                    tints.insert(*entity, Tint(Flock::colour(id))).ok();
+                }
+            }
+        }
+    }
+}
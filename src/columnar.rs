@@ -0,0 +1,228 @@
+use std::{
+    fs::File,
+    path::Path,
+    sync::Arc,
+};
+use arrow::{
+    array::{
+        ArrayRef,
+        Float32Array,
+        Float64Array,
+        UInt64Array,
+    },
+    datatypes::{
+        DataType,
+        Field,
+        Schema,
+        SchemaRef,
+    },
+    record_batch::RecordBatch,
+};
+use parquet::{
+    arrow::ArrowWriter,
+    basic::Compression,
+    errors::Result,
+    file::properties::WriterProperties,
+};
+use crate::{
+    export::TrajectoryRow,
+    metrics::Metrics,
+};
+
+#[derive(Default)]
+struct BoidColumns {
+    tick: Vec<u64>,
+    time: Vec<f64>,
+    id: Vec<u64>,
+    x: Vec<f32>,
+    y: Vec<f32>,
+    z: Vec<f32>,
+    vx: Vec<f32>,
+    vy: Vec<f32>,
+    vz: Vec<f32>,
+    species: Vec<u64>,
+    flock: Vec<Option<u64>>,
+}
+
+impl BoidColumns {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("tick", DataType::UInt64, false),
+            Field::new("time", DataType::Float64, false),
+            Field::new("id", DataType::UInt64, false),
+            Field::new("x", DataType::Float32, false),
+            Field::new("y", DataType::Float32, false),
+            Field::new("z", DataType::Float32, false),
+            Field::new("vx", DataType::Float32, false),
+            Field::new("vy", DataType::Float32, false),
+            Field::new("vz", DataType::Float32, false),
+            Field::new("species", DataType::UInt64, false),
+            Field::new("flock", DataType::UInt64, true),
+        ]))
+    }
+
+    fn push(&mut self, row: &TrajectoryRow) {
+        self.tick.push(row.tick);
+        self.time.push(row.time);
+        self.id.push(row.id as u64);
+        self.x.push(row.x);
+        self.y.push(row.y);
+        self.z.push(row.z);
+        self.vx.push(row.vx);
+        self.vy.push(row.vy);
+        self.vz.push(row.vz);
+        self.species.push(row.species as u64);
+        self.flock.push(row.flock.map(|flock| flock as u64));
+    }
+
+    fn take(&mut self) -> Vec<ArrayRef> {
+        let columns = std::mem::take(self);
+        vec![
+            Arc::new(UInt64Array::from(columns.tick)),
+            Arc::new(Float64Array::from(columns.time)),
+            Arc::new(UInt64Array::from(columns.id)),
+            Arc::new(Float32Array::from(columns.x)),
+            Arc::new(Float32Array::from(columns.y)),
+            Arc::new(Float32Array::from(columns.z)),
+            Arc::new(Float32Array::from(columns.vx)),
+            Arc::new(Float32Array::from(columns.vy)),
+            Arc::new(Float32Array::from(columns.vz)),
+            Arc::new(UInt64Array::from(columns.species)),
+            Arc::new(UInt64Array::from(columns.flock)),
+        ]
+    }
+}
+
+#[derive(Default)]
+struct MetricColumns {
+    tick: Vec<u64>,
+    time: Vec<f64>,
+    population: Vec<u64>,
+    polarization: Vec<f32>,
+    milling: Vec<f32>,
+    nearest_neighbour: Vec<f32>,
+    flock_radius: Vec<f32>,
+    speed_mean: Vec<f32>,
+    speed_std_dev: Vec<f32>,
+    speed_min: Vec<f32>,
+    speed_max: Vec<f32>,
+}
+
+impl MetricColumns {
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("tick", DataType::UInt64, false),
+            Field::new("time", DataType::Float64, false),
+            Field::new("population", DataType::UInt64, false),
+            Field::new("polarization", DataType::Float32, false),
+            Field::new("milling", DataType::Float32, false),
+            Field::new("nearest_neighbour", DataType::Float32, false),
+            Field::new("flock_radius", DataType::Float32, false),
+            Field::new("speed_mean", DataType::Float32, false),
+            Field::new("speed_std_dev", DataType::Float32, false),
+            Field::new("speed_min", DataType::Float32, false),
+            Field::new("speed_max", DataType::Float32, false),
+        ]))
+    }
+
+    fn push(&mut self, metrics: &Metrics) {
+        self.tick.push(metrics.tick);
+        self.time.push(metrics.time);
+        self.population.push(metrics.population as u64);
+        self.polarization.push(metrics.polarization);
+        self.milling.push(metrics.milling);
+        self.nearest_neighbour.push(metrics.nearest_neighbour);
+        self.flock_radius.push(metrics.flock_radius);
+        self.speed_mean.push(metrics.speed.mean);
+        self.speed_std_dev.push(metrics.speed.std_dev);
+        self.speed_min.push(metrics.speed.min);
+        self.speed_max.push(metrics.speed.max);
+    }
+
+    fn take(&mut self) -> Vec<ArrayRef> {
+        let columns = std::mem::take(self);
+        vec![
+            Arc::new(UInt64Array::from(columns.tick)),
+            Arc::new(Float64Array::from(columns.time)),
+            Arc::new(UInt64Array::from(columns.population)),
+            Arc::new(Float32Array::from(columns.polarization)),
+            Arc::new(Float32Array::from(columns.milling)),
+            Arc::new(Float32Array::from(columns.nearest_neighbour)),
+            Arc::new(Float32Array::from(columns.flock_radius)),
+            Arc::new(Float32Array::from(columns.speed_mean)),
+            Arc::new(Float32Array::from(columns.speed_std_dev)),
+            Arc::new(Float32Array::from(columns.speed_min)),
+            Arc::new(Float32Array::from(columns.speed_max)),
+        ]
+    }
+}
+
+pub struct ColumnarExporter {
+    boid_writer: ArrowWriter<File>,
+    metric_writer: ArrowWriter<File>,
+    boids: BoidColumns,
+    metrics: MetricColumns,
+    batch_ticks: u64,
+    buffered_ticks: u64,
+    closed: bool,
+}
+
+impl ColumnarExporter {
+    pub fn create<P: AsRef<Path>>(dir: P, batch_ticks: u64) -> Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let props = || Some(WriterProperties::builder()
+                            .set_compression(Compression::SNAPPY)
+                            .build());
+        Ok(ColumnarExporter {
+            boid_writer: ArrowWriter::try_new(File::create(dir.join("boids.parquet"))?, BoidColumns::schema(), props())?,
+            metric_writer: ArrowWriter::try_new(File::create(dir.join("metrics.parquet"))?, MetricColumns::schema(), props())?,
+            boids: BoidColumns::default(),
+            metrics: MetricColumns::default(),
+            batch_ticks: batch_ticks.max(1),
+            buffered_ticks: 0,
+            closed: false,
+        })
+    }
+
+    pub fn record(&mut self, rows: &[TrajectoryRow], metrics: &Metrics) -> Result<()> {
+        for row in rows {
+            self.boids.push(row);
+        }
+        self.metrics.push(metrics);
+        self.buffered_ticks += 1;
+        if self.buffered_ticks >= self.batch_ticks {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        if self.buffered_ticks == 0 {
+            return Ok(())
+        }
+        self.buffered_ticks = 0;
+        let boids = RecordBatch::try_new(BoidColumns::schema(), self.boids.take())?;
+        self.boid_writer.write(&boids)?;
+        let metrics = RecordBatch::try_new(MetricColumns::schema(), self.metrics.take())?;
+        self.metric_writer.write(&metrics)
+    }
+
+    pub fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(())
+        }
+        self.flush()?;
+        self.closed = true;
+        self.boid_writer.close()?;
+        self.metric_writer.close()
+    }
+}
+
+impl Drop for ColumnarExporter {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            println!("Failed to finish parquet export: {}", e);
+        }
+    }
+}
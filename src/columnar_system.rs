@@ -0,0 +1,47 @@
+use amethyst::{
+    prelude::*,
+    core::SystemDesc,
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        Entities,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    flock::Flock,
+    columnar::ColumnarExporter,
+    export::TrajectoryRow,
+    metrics::Metrics,
+    resources::SimClock,
+};
+
+#[derive(SystemDesc)]
+pub struct ColumnarSystem;
+impl<'s> System<'s> for ColumnarSystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       ReadStorage<'s, Flock>,
+                       Option<Write<'s, ColumnarExporter>>,
+                       Read<'s, Metrics>,
+                       Read<'s, SimClock>,
+                       Entities<'s>);
+
+    fn run(&mut self, (boids, flocks, exporter, metrics, clock, entities): Self::SystemData) {
+        let mut exporter = match exporter {
+            Some(exporter) => exporter,
+            None => return,
+        };
+
+        let rows: Vec<TrajectoryRow> = (&entities, &boids).join()
+            .map(|(entity, boid)| TrajectoryRow::new(clock.tick, clock.seconds, boid, flocks.get(entity).map(Flock::get_id)))
+            .collect();
+        if let Err(e) = exporter.record(&rows, &metrics) {
+            println!("Failed to write parquet batch at tick {}: {}", clock.tick, e);
+        }
+    }
+}
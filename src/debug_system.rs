@@ -0,0 +1,103 @@
+use std::ops::Range;
+use amethyst::{
+    prelude::*,
+    core::{
+        math::{
+            Point2,
+            Point3,
+            RowVector3,
+            Vector3,
+        },
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+    },
+    renderer::{
+        debug_drawing::DebugLines,
+        palette::Srgba,
+    },
+};
+use crate::{
+    boid::Boid,
+    behaviors::Boundary,
+    resources::{
+        BoidInfo,
+        DebugOverlay,
+        Selection,
+        WorldDimensions,
+    },
+    scenario::BoundaryMode,
+};
+
+// Drawn just above the sprites so the lines aren't hidden behind them.
+const Z: f32 = 1.0;
+const FORCE_SCALE: f32 = 5.0;
+
+fn point(v: RowVector3<f32>) -> Point3<f32> {
+    Point3::new(v[0], v[1], Z)
+}
+
+fn rectangle(lines: &mut DebugLines, x: &Range<f32>, y: &Range<f32>, colour: Srgba) {
+    lines.draw_rectangle(Point2::new(x.start, y.start), Point2::new(x.end, y.end), Z, colour);
+}
+
+fn force_colour(name: &str) -> Srgba {
+    match name {
+        "cohesion" => Srgba::new(0.3, 0.6, 1.0, 1.0),
+        "separation" => Srgba::new(1.0, 0.3, 0.3, 1.0),
+        "alignment" => Srgba::new(1.0, 1.0, 0.3, 1.0),
+        "boundary" => Srgba::new(1.0, 0.6, 0.1, 1.0),
+        _ => Srgba::new(1.0, 0.3, 1.0, 1.0),
+    }
+}
+
+#[derive(SystemDesc)]
+pub struct DebugSystem;
+impl<'s> System<'s> for DebugSystem {
+    type SystemData = (Read<'s, DebugOverlay>,
+                       Option<Read<'s, BoidInfo>>,
+                       Read<'s, WorldDimensions>,
+                       Option<Read<'s, BoundaryMode>>,
+                       Read<'s, Selection>,
+                       ReadStorage<'s, Boid>,
+                       Write<'s, DebugLines>);
+
+    fn run(&mut self, (overlay, boid_info, world_dim, boundary, selection, boids, mut lines): Self::SystemData) {
+        if !overlay.enabled {
+            return
+        }
+
+        if let Some(boid_info) = boid_info {
+            for (x, y) in boid_info.qt.nodes() {
+                rectangle(&mut lines, &x, &y, Srgba::new(0.2, 0.5, 0.2, 1.0));
+            }
+        }
+        for region in &world_dim.regions {
+            rectangle(&mut lines, &region.x_range, &region.y_range, Srgba::new(0.2, 0.3, 0.8, 1.0));
+        }
+        if boundary.map_or(false, |boundary| *boundary == BoundaryMode::Steer) {
+            let margin = Boundary::MARGIN;
+            rectangle(&mut lines, &(world_dim.min_x + margin..world_dim.max_x - margin),
+                      &(world_dim.min_y + margin..world_dim.max_y - margin), Srgba::new(0.8, 0.4, 0.1, 1.0));
+        }
+
+        let boid = match selection.entity.and_then(|entity| boids.get(entity)) {
+            Some(boid) => boid,
+            None => return,
+        };
+        let position = point(boid.get_position());
+        lines.draw_circle(position, boid.get_genome().perception_radius, 32, Srgba::new(1.0, 1.0, 1.0, 1.0));
+        let velocity = boid.get_velocity();
+        lines.draw_direction(position, Vector3::new(velocity[0], velocity[1], 0.0), Srgba::new(0.2, 1.0, 0.2, 1.0));
+        for (name, force) in &selection.forces {
+            let force = force * FORCE_SCALE;
+            lines.draw_direction(position, Vector3::new(force[0], force[1], 0.0), force_colour(name));
+        }
+    }
+}
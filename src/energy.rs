@@ -0,0 +1,60 @@
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::ecs::prelude::{
+    Component,
+    VecStorage,
+};
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Energy {
+    value: f32,
+    max: f32,
+    gathered: f32,
+}
+
+impl Energy {
+    pub fn new(value: f32, max: f32) -> Self {
+        Energy {
+            value,
+            max,
+            gathered: 0.0,
+        }
+    }
+
+    pub fn consume(&mut self, amount: f32) {
+        self.value = (self.value - amount).max(0.0);
+    }
+
+    pub fn replenish(&mut self, amount: f32) -> f32 {
+        let eaten = amount.min(self.max - self.value).max(0.0);
+        self.value += eaten;
+        self.gathered += eaten;
+        eaten
+    }
+
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn get_max(&self) -> f32 {
+        self.max
+    }
+
+    pub fn get_gathered(&self) -> f32 {
+        self.gathered
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.value / self.max
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.value <= 0.0
+    }
+}
+
+impl Component for Energy {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,69 @@
+use amethyst::{
+    prelude::*,
+    core::{
+        timing::Time,
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        WriteStorage,
+        Entities,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    energy::Energy,
+    food::Food,
+    age::Age,
+    scenario::EnergyConfig,
+    resources::{
+        BoidInfo,
+        DeathLog,
+        DeathRecord,
+    },
+};
+
+#[derive(SystemDesc)]
+pub struct EnergySystem;
+impl<'s> System<'s> for EnergySystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       WriteStorage<'s, Energy>,
+                       WriteStorage<'s, Food>,
+                       Read<'s, EnergyConfig>,
+                       Write<'s, BoidInfo>,
+                       ReadStorage<'s, Age>,
+                       Option<Write<'s, DeathLog>>,
+                       Read<'s, Time>,
+                       Entities<'s>);
+
+    fn run(&mut self, (boids, mut energies, mut food, config, mut boid_info, ages, mut death_log, time, entities): Self::SystemData) {
+        let delta = time.delta_seconds();
+        for (entity, boid, energy) in (&entities, &boids, &mut energies).join() {
+            let speed = boid.get_velocity().magnitude();
+            energy.consume((config.base_cost + config.speed_cost * speed) * delta);
+
+            for patch in (&mut food).join() {
+                if (patch.get_position() - boid.get_position()).magnitude() < patch.get_radius() {
+                    let wanted = (config.eat_rate * delta).min(energy.get_max() - energy.get_value());
+                    energy.replenish(patch.take(wanted));
+                }
+            }
+
+            if energy.is_depleted() {
+                if let Some(log) = death_log.as_mut() {
+                    log.0.push(DeathRecord::new(boid, ages.get(entity), Some(energy)));
+                }
+                boid_info.qt.remove(boid);
+                if let Err(e) = entities.delete(entity) {
+                    println!("Failed to despawn starved boid: {}", e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+};
+use rand::{
+    Rng,
+    SeedableRng,
+};
+use rand_pcg::Pcg32;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::{
+    prelude::*,
+    core::timing::Time,
+    config::Config,
+    ecs::prelude::Join,
+};
+use crate::{
+    boid::Boid,
+    age::Age,
+    energy::Energy,
+    genome::Genome,
+    resources::{
+        WorldConfig,
+        CurrentState,
+        DeathLog,
+    },
+    scenario::Scenario,
+    script::Script,
+    simulator::Simulator,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvolutionConfig {
+    pub generations: usize,
+    pub generation_seconds: f32,
+    pub time_step: f32,
+    #[serde(default)]
+    pub species: Option<String>,
+    pub survival_weight: f32,
+    pub food_weight: f32,
+    pub elite_fraction: f32,
+    pub mutation_rate: f32,
+    pub mutation_strength: f32,
+    pub output: PathBuf,
+    #[serde(default)]
+    pub network_output: Option<PathBuf>,
+}
+
+impl Default for EvolutionConfig {
+    fn default() -> Self {
+        EvolutionConfig {
+            generations: 20,
+            generation_seconds: 60.0,
+            time_step: 1.0 / 60.0,
+            species: None,
+            survival_weight: 1.0,
+            food_weight: 1.0,
+            elite_fraction: 0.2,
+            mutation_rate: 0.3,
+            mutation_strength: 0.2,
+            output: PathBuf::from("evolved_genome.ron"),
+            network_output: None,
+        }
+    }
+}
+
+pub struct EvolutionState {
+    config: EvolutionConfig,
+    scenario: Scenario,
+    world_config: WorldConfig,
+    species: usize,
+    genomes: Vec<Genome>,
+    generation: usize,
+    elapsed: f32,
+    best: Option<(f32, Genome)>,
+    script: Option<Arc<Script>>,
+    rng: Pcg32,
+}
+
+impl EvolutionState {
+    pub fn new(config: EvolutionConfig, scenario: Scenario, world_config: WorldConfig) -> Self {
+        let species = scenario.species_index(config.species.as_ref().map(String::as_str)).unwrap_or(0);
+        let population = scenario.spawn_groups.iter()
+            .filter(|group| scenario.species_index(group.species.as_ref().map(String::as_str)) == Some(species))
+            .map(|group| group.count)
+            .sum::<usize>()
+            .max(1);
+        let species_config = &scenario.species_list()[species];
+        let mut rng = Pcg32::seed_from_u64(scenario.seed.unwrap_or_else(|| rand::thread_rng().gen()));
+        let mut genome = species_config.genome.clone();
+        if genome.network.is_none() {
+            genome.network = species_config.policy.network(&mut rng).unwrap_or(None).map(Arc::new);
+        }
+        let genomes = (0..population)
+            .map(|_| genome.mutate(&mut rng, 1.0, config.mutation_strength))
+            .collect();
+
+        EvolutionState {
+            config,
+            scenario,
+            world_config,
+            species,
+            genomes,
+            generation: 0,
+            elapsed: 0.0,
+            best: None,
+            script: None,
+            rng,
+        }
+    }
+
+    pub fn with_script(mut self, script: Option<Arc<Script>>) -> Self {
+        self.script = script;
+        self
+    }
+
+    fn start_generation(&mut self, world: &mut World) {
+        world.delete_all();
+        world.write_resource::<DeathLog>().0.clear();
+        Simulator::init_scenario(world, &self.scenario, &self.world_config, self.script.clone(), None);
+        {
+            let mut boids = world.write_storage::<Boid>();
+            for (boid, genome) in (&mut boids).join()
+                .filter(|boid| boid.get_species() == self.species)
+                .zip(self.genomes.iter().cycle()) {
+                boid.set_genome(genome.clone());
+            }
+        }
+        Simulator::rebuild_spatial_index(world);
+        self.elapsed = 0.0;
+    }
+
+    fn fitness(&self, age: f32, gathered: f32) -> f32 {
+        self.config.survival_weight * age + self.config.food_weight * gathered
+    }
+
+    fn evaluate(&mut self, world: &mut World) -> Vec<(f32, Genome)> {
+        let mut scored: Vec<(f32, Genome)> = world.write_resource::<DeathLog>().0.drain(..)
+            .filter(|record| record.species == self.species)
+            .map(|record| (self.fitness(record.age, record.gathered), record.genome))
+            .collect();
+
+        let boids = world.read_storage::<Boid>();
+        let ages = world.read_storage::<Age>();
+        let energies = world.read_storage::<Energy>();
+        for (boid, age, energy) in (&boids, ages.maybe(), energies.maybe()).join() {
+            if boid.get_species() == self.species {
+                let age = age.map_or(self.elapsed, Age::get_seconds);
+                let gathered = energy.map_or(0.0, Energy::get_gathered);
+                scored.push((self.fitness(age, gathered), boid.get_genome().clone()));
+            }
+        }
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored
+    }
+
+    fn next_generation(&mut self, scored: &[(f32, Genome)]) {
+        let population = self.genomes.len();
+        let elites = ((population as f32 * self.config.elite_fraction).ceil() as usize)
+            .max(1)
+            .min(scored.len());
+        if elites == 0 {
+            return
+        }
+
+        let (rng, config) = (&mut self.rng, &self.config);
+        self.genomes = (0..population)
+            .map(|i| {
+                if i < elites {
+                    scored[i].1.clone()
+                }
+                else {
+                    scored[rng.gen_range(0, elites)].1.mutate(rng, config.mutation_rate, config.mutation_strength)
+                }
+            })
+            .collect();
+    }
+}
+
+impl SimpleState for EvolutionState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        data.world.insert(DeathLog::default());
+        self.start_generation(data.world);
+        *data.world.write_resource::<CurrentState>() = CurrentState::Running;
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        data.world.write_resource::<Time>().set_delta_seconds(self.config.time_step);
+        self.elapsed += self.config.time_step;
+        if self.elapsed < self.config.generation_seconds {
+            return Trans::None
+        }
+
+        let scored = self.evaluate(data.world);
+        if let Some((fitness, genome)) = scored.first() {
+            let mean = scored.iter().map(|(f, _)| f).sum::<f32>() / scored.len() as f32;
+            println!("Generation {}: best {:.2} mean {:.2} {:?}", self.generation, fitness, mean, genome);
+            if self.best.as_ref().map_or(true, |(best, _)| fitness > best) {
+                self.best = Some((*fitness, genome.clone()));
+            }
+        }
+
+        self.generation += 1;
+        if self.generation >= self.config.generations {
+            if let Some((fitness, genome)) = &self.best {
+                println!("Best genome (fitness {:.2}) written to {:?}", fitness, self.config.output);
+                if let Err(e) = genome.write(&self.config.output) {
+                    println!("Failed to write genome: {}", e);
+                }
+                if let (Some(network), Some(path)) = (&genome.network, &self.config.network_output) {
+                    if let Err(e) = network.write(path) {
+                        println!("Failed to write network weights: {}", e);
+                    }
+                }
+            }
+            return Trans::Quit
+        }
+
+        self.next_generation(&scored);
+        self.start_generation(data.world);
+        Trans::None
+    }
+}
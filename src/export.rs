@@ -0,0 +1,115 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use crate::boid::Boid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Some(ExportFormat::Csv),
+            Some("jsonl") | Some("ndjson") => Some(ExportFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrajectoryRow {
+    pub tick: u64,
+    pub time: f64,
+    pub id: usize,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub vz: f32,
+    pub species: usize,
+    pub flock: Option<usize>,
+}
+
+impl TrajectoryRow {
+    pub fn new(tick: u64, time: f64, boid: &Boid, flock: Option<usize>) -> Self {
+        let pos = boid.get_position();
+        let vel = boid.get_velocity();
+        TrajectoryRow {
+            tick,
+            time,
+            id: boid.id,
+            x: pos[0],
+            y: pos[1],
+            z: pos[2],
+            vx: vel[0],
+            vy: vel[1],
+            vz: vel[2],
+            species: boid.get_species(),
+            flock,
+        }
+    }
+}
+
+pub struct Exporter {
+    writer: BufWriter<File>,
+    format: ExportFormat,
+    interval: u64,
+    ids: Option<HashSet<usize>>,
+}
+
+impl Exporter {
+    pub fn create<P: AsRef<Path>>(path: P, interval: u64, ids: Option<HashSet<usize>>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let format = ExportFormat::from_path(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "export path must end in .csv or .jsonl"))?;
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == ExportFormat::Csv {
+            writeln!(writer, "tick,time,id,x,y,z,vx,vy,vz,species,flock")?;
+        }
+        Ok(Exporter {
+            writer,
+            format,
+            interval: interval.max(1),
+            ids,
+        })
+    }
+
+    pub fn wants(&self, tick: u64) -> bool {
+        tick % self.interval == 0
+    }
+
+    pub fn includes(&self, id: usize) -> bool {
+        self.ids.as_ref().map_or(true, |ids| ids.contains(&id))
+    }
+
+    pub fn write(&mut self, row: &TrajectoryRow) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Csv => writeln!(self.writer, "{},{},{},{},{},{},{},{},{},{},{}",
+                                          row.tick, row.time, row.id, row.x, row.y, row.z, row.vx, row.vy, row.vz, row.species,
+                                          row.flock.map_or(String::new(), |flock| flock.to_string())),
+            ExportFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, row)?;
+                writeln!(self.writer)
+            },
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,57 @@
+use amethyst::{
+    prelude::*,
+    core::SystemDesc,
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        Entities,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    flock::Flock,
+    export::{
+        Exporter,
+        TrajectoryRow,
+    },
+    resources::SimClock,
+};
+
+#[derive(SystemDesc)]
+pub struct ExportSystem;
+impl<'s> System<'s> for ExportSystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       ReadStorage<'s, Flock>,
+                       Option<Write<'s, Exporter>>,
+                       Read<'s, SimClock>,
+                       Entities<'s>);
+
+    fn run(&mut self, (boids, flocks, exporter, clock, entities): Self::SystemData) {
+        let mut exporter = match exporter {
+            Some(exporter) => exporter,
+            None => return,
+        };
+        if !exporter.wants(clock.tick) {
+            return
+        }
+
+        for (entity, boid) in (&entities, &boids).join() {
+            if !exporter.includes(boid.id) {
+                continue;
+            }
+            let row = TrajectoryRow::new(clock.tick, clock.seconds, boid, flocks.get(entity).map(Flock::get_id));
+            if let Err(e) = exporter.write(&row) {
+                println!("Failed to export tick {}: {}", clock.tick, e);
+                return
+            }
+        }
+        if let Err(e) = exporter.flush() {
+            println!("Failed to flush export: {}", e);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+    VecDeque,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::{
+    ecs::prelude::{
+        Component,
+        VecStorage,
+        Entity,
+    },
+    renderer::palette::Srgba,
+};
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Flock {
+    id: usize,
+}
+
+impl Flock {
+    pub fn new(id: usize) -> Self {
+        Flock {
+            id,
+        }
+    }
+
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn colour(id: usize) -> Srgba {
+        let hue = (id as f32 * 0.618_034).fract() * 6.0;
+        let x = 1.0 - (hue % 2.0 - 1.0).abs();
+        let (r, g, b) = match hue as usize {
+            0 => (1.0, x, 0.0),
+            1 => (x, 1.0, 0.0),
+            2 => (0.0, 1.0, x),
+            3 => (0.0, x, 1.0),
+            4 => (x, 0.0, 1.0),
+            _ => (1.0, 0.0, x),
+        };
+        Srgba::new(r, g, b, 1.0)
+    }
+}
+
+impl Component for Flock {
+    type Storage = VecStorage<Self>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlockSummary {
+    pub id: usize,
+    pub species: usize,
+    pub size: usize,
+    pub born: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FlockEvent {
+    Formed { id: usize },
+    Split { from: usize, into: Vec<usize> },
+    Merged { from: Vec<usize>, into: usize },
+    Dissolved { id: usize, lifetime: f64 },
+}
+
+const MAX_EVENTS: usize = 256;
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Flocks {
+    pub flocks: Vec<FlockSummary>,
+    // The latest splits, merges, formations and dissolutions, stamped with the simulation time they were seen.
+    pub events: VecDeque<(f64, FlockEvent)>,
+    event_count: u64,
+    pub ticks: u64,
+    next_id: usize,
+}
+
+impl Flocks {
+    fn push_event(&mut self, time: f64, event: FlockEvent) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back((time, event));
+        self.event_count += 1;
+    }
+
+    // Total number of events ever seen, so readers can tell which ones are new.
+    pub fn event_count(&self) -> u64 {
+        self.event_count
+    }
+
+    pub fn events_since(&self, count: u64) -> impl Iterator<Item = &(f64, FlockEvent)> {
+        let new = (self.event_count - count.min(self.event_count)) as usize;
+        self.events.iter().skip(self.events.len().saturating_sub(new))
+    }
+
+    pub fn track(&mut self, clusters: &[(usize, Vec<(Entity, Option<usize>)>)], time: f64) -> Vec<usize> {
+        let mut ordered: Vec<usize> = (0..clusters.len()).collect();
+        ordered.sort_by(|a, b| clusters[*b].1.len().cmp(&clusters[*a].1.len()));
+
+        let previous: HashMap<usize, f64> = self.flocks.iter().map(|flock| (flock.id, flock.born)).collect();
+        let mut ids = vec![0; clusters.len()];
+        let mut claimed = HashSet::new();
+        let mut sources = vec![vec![]; clusters.len()];
+        for cluster in ordered {
+            let mut votes = HashMap::new();
+            for (_, old) in &clusters[cluster].1 {
+                if let Some(old) = old.filter(|old| previous.contains_key(old)) {
+                    *votes.entry(old).or_insert(0) += 1;
+                }
+            }
+            let mut candidates: Vec<(usize, usize)> = votes.into_iter().collect();
+            candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            sources[cluster] = candidates.iter().map(|(old, _)| *old).collect();
+
+            ids[cluster] = match candidates.iter().find(|(old, _)| !claimed.contains(old)) {
+                Some((old, _)) => *old,
+                None => {
+                    self.next_id += 1;
+                    self.next_id
+                },
+            };
+            claimed.insert(ids[cluster]);
+        }
+
+        let mut descendants: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (cluster, from) in sources.iter().enumerate() {
+            for old in from {
+                descendants.entry(*old).or_insert_with(Vec::new).push(ids[cluster]);
+            }
+            match from.len() {
+                0 => self.push_event(time, FlockEvent::Formed { id: ids[cluster] }),
+                1 => {},
+                _ => self.push_event(time, FlockEvent::Merged { from: from.clone(), into: ids[cluster] }),
+            }
+        }
+        for (old, born) in &previous {
+            match descendants.get(old) {
+                Some(into) if into.len() > 1 => self.push_event(time, FlockEvent::Split { from: *old, into: into.clone() }),
+                // Absorbed into another flock, which the merge event already covers.
+                Some(_) => {},
+                None => self.push_event(time, FlockEvent::Dissolved { id: *old, lifetime: time - born }),
+            }
+        }
+
+        self.flocks = clusters.iter()
+            .zip(ids.iter())
+            .map(|((species, members), id)| FlockSummary {
+                id: *id,
+                species: *species,
+                size: members.len(),
+                born: previous.get(id).cloned().unwrap_or(time),
+            })
+            .collect();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst::ecs::prelude::{
+        Builder,
+        World,
+        WorldExt,
+    };
+    use super::*;
+
+    fn members(world: &mut World, old: &[Option<usize>]) -> Vec<(Entity, Option<usize>)> {
+        old.iter().map(|old| (world.create_entity().build(), *old)).collect()
+    }
+
+    fn events(flocks: &Flocks) -> Vec<FlockEvent> {
+        flocks.events.iter().map(|(_, event)| event.clone()).collect()
+    }
+
+    #[test]
+    fn new_clusters_form_flocks() {
+        let mut world = World::new();
+        let mut flocks = Flocks::default();
+        let clusters = vec![(0, members(&mut world, &[None, None])), (0, members(&mut world, &[None]))];
+        let ids = flocks.track(&clusters, 1.0);
+        assert_ne!(ids[0], ids[1]);
+        assert_eq!(flocks.flocks.len(), 2);
+        assert!(events(&flocks).iter().all(|event| matches!(event, FlockEvent::Formed { .. })));
+    }
+
+    #[test]
+    fn split_keeps_the_id_on_the_larger_part() {
+        let mut world = World::new();
+        let mut flocks = Flocks::default();
+        let id = flocks.track(&[(0, members(&mut world, &[None; 5]))], 1.0)[0];
+        let ids = flocks.track(&[(0, members(&mut world, &[Some(id); 2])), (0, members(&mut world, &[Some(id); 3]))], 2.0);
+        assert_eq!(ids[1], id);
+        assert_ne!(ids[0], id);
+        let events = events(&flocks);
+        assert!(matches!(events.last(), Some(FlockEvent::Split { from, into }) if *from == id && into.len() == 2));
+        assert!(!events.iter().any(|event| matches!(event, FlockEvent::Dissolved { .. })));
+        assert_eq!(flocks.flocks.iter().find(|flock| flock.id == id).map(|flock| flock.born), Some(1.0));
+    }
+
+    #[test]
+    fn merge_is_not_reported_as_a_dissolution() {
+        let mut world = World::new();
+        let mut flocks = Flocks::default();
+        let ids = flocks.track(&[(0, members(&mut world, &[None; 3])), (0, members(&mut world, &[None; 2]))], 1.0);
+        let merged = flocks.track(&[(0, members(&mut world, &[Some(ids[0]), Some(ids[0]), Some(ids[0]), Some(ids[1])]))], 2.0);
+        assert_eq!(merged, vec![ids[0]]);
+        let events = events(&flocks);
+        assert!(matches!(events.last(), Some(FlockEvent::Merged { from, into }) if *into == ids[0] && from.contains(&ids[1])));
+        assert!(!events.iter().any(|event| matches!(event, FlockEvent::Dissolved { .. })));
+    }
+
+    #[test]
+    fn vanished_flock_dissolves_with_its_lifetime() {
+        let mut world = World::new();
+        let mut flocks = Flocks::default();
+        flocks.track(&[(0, members(&mut world, &[None; 3]))], 1.0);
+        flocks.track(&[], 4.5);
+        assert!(flocks.flocks.is_empty());
+        assert!(matches!(events(&flocks).last(), Some(FlockEvent::Dissolved { lifetime, .. }) if (*lifetime - 3.5).abs() < 1e-9));
+    }
+
+    #[test]
+    fn event_log_is_bounded_and_counts_everything() {
+        let mut world = World::new();
+        let mut flocks = Flocks::default();
+        for tick in 0..MAX_EVENTS + 10 {
+            flocks.track(&[(0, members(&mut world, &[None]))], tick as f64);
+        }
+        // Each round dissolves the previous flock and forms a new one.
+        let total = 2 * (MAX_EVENTS + 10) as u64 - 1;
+        assert_eq!(flocks.events.len(), MAX_EVENTS);
+        assert_eq!(flocks.event_count(), total);
+        assert_eq!(flocks.events_since(total - 3).count(), 3);
+        assert_eq!(flocks.events_since(0).count(), MAX_EVENTS);
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::{
+    ecs::prelude::{
+        Component,
+        DenseVecStorage,
+    },
+    core::math::RowVector3,
+};
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Food {
+    position: RowVector3<f32>,
+    radius: f32,
+    amount: f32,
+    capacity: f32,
+    regrowth: f32,
+}
+
+impl Food {
+    pub fn new(position: RowVector3<f32>, radius: f32, capacity: f32, regrowth: f32) -> Self {
+        Food {
+            position,
+            radius,
+            amount: capacity,
+            capacity,
+            regrowth,
+        }
+    }
+
+    pub fn take(&mut self, amount: f32) -> f32 {
+        let taken = amount.min(self.amount);
+        self.amount -= taken;
+        taken
+    }
+
+    pub fn regrow(&mut self, factor: f32, delta_seconds: f32) {
+        self.amount = (self.amount + self.regrowth * factor * delta_seconds).min(self.capacity);
+    }
+
+    pub fn get_position(&self) -> RowVector3<f32> {
+        self.position
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn get_amount(&self) -> f32 {
+        self.amount
+    }
+}
+
+impl Component for Food {
+    type Storage = DenseVecStorage<Self>;
+}
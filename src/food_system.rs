@@ -0,0 +1,34 @@
+use amethyst::{
+    prelude::*,
+    core::{
+        timing::Time,
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        WriteStorage,
+        Join,
+    },
+};
+use crate::{
+    food::Food,
+    resources::Weather,
+};
+
+#[derive(SystemDesc)]
+pub struct FoodSystem;
+impl<'s> System<'s> for FoodSystem {
+    type SystemData = (WriteStorage<'s, Food>,
+                       Read<'s, Weather>,
+                       Read<'s, Time>);
+
+    fn run(&mut self, (mut food, weather, time): Self::SystemData) {
+        let factor = weather.food_growth_factor();
+        for patch in (&mut food).join() {
+            patch.regrow(factor, time.delta_seconds());
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use rand::Rng;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use crate::steering::Network;
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Genome {
+    pub cohesion: f32,
+    pub alignment: f32,
+    pub separation: f32,
+    #[serde(default = "Genome::default_separation_radius")]
+    pub separation_radius: f32,
+    pub perception_radius: f32,
+    pub max_speed: f32,
+    // Shared so cloning a genome (every boid, every frame) doesn't copy the weights.
+    #[serde(default)]
+    pub network: Option<Arc<Network>>,
+}
+
+impl Default for Genome {
+    fn default() -> Self {
+        Genome {
+            cohesion: 1.0,
+            alignment: 1.0,
+            separation: 1.0,
+            separation_radius: Genome::default_separation_radius(),
+            perception_radius: 250.0,
+            max_speed: 50.0,
+            network: None,
+        }
+    }
+}
+
+impl Genome {
+    fn default_separation_radius() -> f32 {
+        31.622_776
+    }
+
+    pub fn mutate<R: Rng>(&self, rng: &mut R, rate: f32, strength: f32) -> Genome {
+        let mut gene = |value: f32| {
+            if rng.gen::<f32>() < rate {
+                (value * (1.0 + rng.gen_range(-strength, strength))).max(0.0)
+            }
+            else {
+                value
+            }
+        };
+        Genome {
+            cohesion: gene(self.cohesion),
+            alignment: gene(self.alignment),
+            separation: gene(self.separation),
+            separation_radius: gene(self.separation_radius),
+            perception_radius: gene(self.perception_radius),
+            max_speed: gene(self.max_speed),
+            network: self.network.as_ref().map(|network| Arc::new(network.mutate(rng, rate, strength))),
+        }
+    }
+}
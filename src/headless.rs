@@ -0,0 +1,147 @@
+use std::{
+    fs::File,
+    path::PathBuf,
+    sync::Arc,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::{
+    prelude::*,
+    core::timing::Time,
+};
+use crate::{
+    metrics::Metrics,
+    flock::Flocks,
+    resources::{
+        WorldConfig,
+        CurrentState,
+    },
+    api::ApiServer,
+    paused::Paused,
+    scenario::Scenario,
+    script::Script,
+    simulator::Simulator,
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub ticks: u64,
+    pub population: usize,
+    pub polarization: f32,
+    pub milling: f32,
+    pub nearest_neighbour: f32,
+    pub flock_radius: f32,
+    pub speed_mean: f32,
+    pub flocks: usize,
+    #[serde(skip)]
+    samples: usize,
+}
+
+impl RunSummary {
+    fn add(&mut self, metrics: &Metrics) {
+        self.samples += 1;
+        let n = self.samples as f32;
+        let mean = |current: f32, value: f32| current + (value - current) / n;
+        self.polarization = mean(self.polarization, metrics.polarization);
+        self.milling = mean(self.milling, metrics.milling);
+        self.nearest_neighbour = mean(self.nearest_neighbour, metrics.nearest_neighbour);
+        self.flock_radius = mean(self.flock_radius, metrics.flock_radius);
+        self.speed_mean = mean(self.speed_mean, metrics.speed.mean);
+    }
+}
+
+pub struct HeadlessState {
+    scenario: Scenario,
+    world_config: WorldConfig,
+    duration: f32,
+    time_step: f32,
+    summary: Option<PathBuf>,
+    elapsed: f32,
+    result: RunSummary,
+    script: Option<Arc<Script>>,
+    api: Option<(u16, u64)>,
+}
+
+impl HeadlessState {
+    pub fn new(scenario: Scenario, world_config: WorldConfig, duration: f32, time_step: f32, summary: Option<PathBuf>) -> Self {
+        HeadlessState {
+            scenario,
+            world_config,
+            duration,
+            time_step,
+            summary,
+            elapsed: 0.0,
+            result: RunSummary::default(),
+            script: None,
+            api: None,
+        }
+    }
+
+    pub fn with_script(mut self, script: Option<Arc<Script>>) -> Self {
+        self.script = script;
+        self
+    }
+
+    pub fn with_api(mut self, port: Option<u16>, interval: u64) -> Self {
+        self.api = port.map(|port| (port, interval));
+        self
+    }
+
+    fn finish(&mut self, world: &World) {
+        let metrics = world.read_resource::<Metrics>();
+        self.result.ticks = metrics.tick;
+        self.result.population = metrics.population;
+        self.result.flocks = world.read_resource::<Flocks>().flocks.len();
+        match &self.summary {
+            Some(path) => {
+                let written = File::create(path)
+                    .map_err(serde_json::Error::io)
+                    .and_then(|file| serde_json::to_writer(file, &self.result));
+                if let Err(e) = written {
+                    println!("Failed to write run summary to {}: {}", path.display(), e);
+                }
+            },
+            None => println!("{:?}", self.result),
+        }
+    }
+}
+
+impl SimpleState for HeadlessState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        Simulator::init_scenario(data.world, &self.scenario, &self.world_config, self.script.clone(), None);
+        if let Some((port, interval)) = self.api {
+            match ApiServer::start(port, interval) {
+                Ok(server) => data.world.insert(server),
+                Err(e) => println!("Failed to start API on port {}: {}", port, e),
+            }
+        }
+        *data.world.write_resource::<CurrentState>() = CurrentState::Running;
+    }
+
+    fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        *data.world.write_resource::<CurrentState>() = CurrentState::Running;
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let request = data.world.try_fetch_mut::<ApiServer>().and_then(|mut server| server.take_pause_request());
+        if request == Some(true) {
+            return Trans::Push(Box::new(Paused::default()))
+        }
+        data.world.write_resource::<Time>().set_delta_seconds(self.time_step);
+        self.elapsed += self.time_step;
+        // Only the second half of the run is averaged so the summary reflects
+        // the settled flock rather than the initial spawn.
+        if self.elapsed >= self.duration / 2.0 {
+            self.result.add(&data.world.read_resource::<Metrics>());
+        }
+        // A non-positive duration keeps a live run going until the process is stopped.
+        if self.duration <= 0.0 || self.elapsed < self.duration {
+            return Trans::None
+        }
+
+        self.finish(data.world);
+        Trans::Quit
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use amethyst::{
+    prelude::*,
+    assets::{
+        AssetStorage,
+        Loader,
+    },
+    ecs::prelude::Entity,
+    renderer::resources::Tint,
+    ui::{
+        get_default_font,
+        Anchor,
+        FontAsset,
+        LineMode,
+        UiText,
+        UiTransform,
+    },
+};
+
+pub struct Inspector {
+    pub text: Entity,
+    pub highlighted: HashMap<Entity, Option<Tint>>,
+}
+
+impl Inspector {
+    pub fn create(world: &mut World) -> Self {
+        let font = get_default_font(&world.read_resource::<Loader>(), &world.read_resource::<AssetStorage<FontAsset>>());
+        let mut text = UiText::new(font, String::new(), [1.0, 1.0, 1.0, 1.0], 16.0);
+        text.line_mode = LineMode::Wrap;
+        text.align = Anchor::TopLeft;
+        let transform = UiTransform::new("inspector".to_string(), Anchor::TopLeft, Anchor::TopLeft,
+                                         10.0, -10.0, 1.0, 420.0, 240.0);
+        Inspector {
+            text: world.create_entity().with(transform).with(text).build(),
+            highlighted: HashMap::new(),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use amethyst::{
+    prelude::*,
+    core::{
+        math::RowVector3,
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        WriteStorage,
+        Entities,
+        Entity,
+    },
+    renderer::{
+        palette::Srgba,
+        resources::Tint,
+    },
+    ui::UiText,
+};
+use crate::{
+    boid::Boid,
+    inspector::Inspector,
+    resources::{
+        Selection,
+        SpeciesList,
+    },
+};
+
+fn vector(v: &RowVector3<f32>) -> String {
+    format!("({:.2}, {:.2}) |{:.2}|", v[0], v[1], v.magnitude())
+}
+
+#[derive(SystemDesc)]
+pub struct InspectorSystem;
+impl<'s> System<'s> for InspectorSystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       WriteStorage<'s, Tint>,
+                       WriteStorage<'s, UiText>,
+                       Option<Write<'s, Inspector>>,
+                       Write<'s, Selection>,
+                       Read<'s, SpeciesList>,
+                       Entities<'s>);
+
+    fn run(&mut self, (boids, mut tints, mut texts, inspector, mut selection, species_list, entities): Self::SystemData) {
+        let mut inspector = match inspector {
+            Some(inspector) => inspector,
+            None => return,
+        };
+        let selected = selection.entity.filter(|entity| entities.is_alive(*entity) && boids.contains(*entity));
+        if selected.is_none() && selection.entity.is_some() {
+            selection.select(None);
+        }
+
+        let mut highlights = HashMap::new();
+        if let Some(entity) = selected {
+            for neighbour in &selection.neighbours {
+                highlights.insert(*neighbour, Tint(Srgba::new(1.0, 0.9, 0.2, 1.0)));
+            }
+            highlights.insert(entity, Tint(Srgba::new(1.0, 0.2, 0.2, 1.0)));
+        }
+
+        // Put back whatever tint (flock colour or none) a boid had before it was highlighted.
+        let stale: Vec<Entity> = inspector.highlighted.keys().filter(|entity| !highlights.contains_key(entity)).cloned().collect();
+        for entity in stale {
+            let original = inspector.highlighted.remove(&entity).and_then(|tint| tint);
+            if !entities.is_alive(entity) {
+                continue;
+            }
+            match original {
+                Some(tint) => {
+                    tints.insert(entity, tint).ok();
+                },
+                None => {
+                    tints.remove(entity);
+                },
+            }
+        }
+        for (entity, tint) in highlights {
+            if !entities.is_alive(entity) {
+                continue;
+            }
+            if !inspector.highlighted.contains_key(&entity) {
+                inspector.highlighted.insert(entity, tints.get(entity).cloned());
+            }
+            tints.insert(entity, tint).ok();
+        }
+
+        let text = match selected.and_then(|entity| boids.get(entity)) {
+            Some(boid) => {
+                let species = species_list.0.get(boid.get_species()).map_or("unknown", |species| species.name.as_str());
+                let mut lines = vec![
+                    format!("boid {} ({})", boid.id, species),
+                    format!("position ({:.1}, {:.1})", boid.get_position()[0], boid.get_position()[1]),
+                    format!("velocity {}", vector(&boid.get_velocity())),
+                    format!("speed {:.2}", boid.get_velocity().magnitude()),
+                    format!("neighbours {}", selection.neighbours.len()),
+                ];
+                lines.extend(selection.forces.iter().map(|(name, force)| format!("{} {}", name, vector(force))));
+                lines.join("\n")
+            },
+            None => String::new(),
+        };
+        if let Some(ui_text) = texts.get_mut(inspector.text) {
+            ui_text.text = text;
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use amethyst::{
+    prelude::*,
+    input::{
+        InputHandler,
+        StringBindings,
+    },
+    derive::SystemDesc,
+    core::{
+        transform::Transform,
+        timing::Time,
+        SystemDesc,
+    },
+    ecs::prelude::{
+        System,
+        SystemData,
+        ReadStorage,
+        WriteStorage,
+        Read,
+        Write,
+        ReadExpect,
+        Join,
+    },
+    renderer::Camera,
+    window::ScreenDimensions,
+};
+use crate::{
+    boid::Boid,
+    resources::BoidInfo,
+    simulator::Simulator,
+};
+
+const RADIUS: f32 = 200.0;
+const STRENGTH: f32 = 80.0;
+
+#[derive(SystemDesc)]
+pub struct InteractionSystem;
+impl<'s> System<'s> for InteractionSystem {
+    type SystemData = (WriteStorage<'s, Boid>,
+                       ReadStorage<'s, Camera>,
+                       ReadStorage<'s, Transform>,
+                       Read<'s, InputHandler<StringBindings>>,
+                       ReadExpect<'s, ScreenDimensions>,
+                       Read<'s, Time>,
+                       Write<'s, BoidInfo>);
+
+    fn run(&mut self, (mut boids, cameras, transforms, input, screen, time, mut boid_info): Self::SystemData) {
+        let is_down = |action: &str| input.action_is_down(action).unwrap_or(false);
+        // Modified clicks spawn, place obstacles, select or pan instead of dragging the flock.
+        if is_down("spawn_group") || is_down("place_obstacle") || is_down("select") || is_down("drag_pan") {
+            return
+        }
+        let sign = if is_down("attract") {
+            1.0
+        }
+        else if is_down("repel") {
+            -1.0
+        }
+        else {
+            return
+        };
+
+        let cursor = match (input.mouse_position(), (&cameras, &transforms).join().next()) {
+            (Some(mouse), Some((camera, transform))) => Simulator::screen_to_world(camera, transform, &screen, mouse),
+            _ => None,
+        };
+        let cursor = match cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        for boid in (&mut boids).join() {
+            let offset = cursor - boid.get_position();
+            let distance = offset.magnitude();
+            if distance > RADIUS || distance < std::f32::EPSILON {
+                continue;
+            }
+            // Pull fades out towards the edge of the radius so the flock isn't torn apart.
+            let falloff = 1.0 - distance / RADIUS;
+            let velocity = boid.get_velocity() + offset / distance * (sign * STRENGTH * falloff * time.delta_seconds());
+            boid.set_velocity(velocity);
+            // The steering systems read boids back out of the spatial index, so the push has to land there too.
+            if boid_info.qt.remove(boid) {
+                boid_info.qt.insert(boid.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use rand::Rng;
+use amethyst::{
+    prelude::*,
+    core::{
+        transform::Transform,
+        math::{
+            RowVector3,
+            Vector3,
+        },
+        timing::Time,
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        WriteStorage,
+        Entities,
+        Entity,
+        Join,
+    },
+    renderer::SpriteRender,
+};
+use crate::{
+    boid::Boid,
+    boid_system::BoidSystem,
+    energy::Energy,
+    age::Age,
+    wander::Wander,
+    resources::{
+        BoidInfo,
+        BoidIds,
+        BoidSprite,
+        WorldDimensions,
+        SpeciesList,
+        SpawnRequests,
+        DeathLog,
+        DeathRecord,
+        WanderSeeder,
+        SimRng,
+    },
+    scenario::{
+        BoundaryMode,
+        EnergyConfig,
+        PopulationConfig,
+        WanderConfig,
+    },
+};
+
+struct Birth {
+    boid: Boid,
+    energy: Option<Energy>,
+    sprite: Option<SpriteRender>,
+}
+
+#[derive(SystemDesc)]
+pub struct LifecycleSystem;
+impl LifecycleSystem {
+    fn offspring_position<R: Rng>(rng: &mut R, parent: &Boid, radius: f32) -> RowVector3<f32> {
+        let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+        let dist = rng.gen_range(0.0, radius.max(std::f32::EPSILON));
+        parent.get_position() + RowVector3::new(dist * angle.cos(), dist * angle.sin(), 0.0)
+    }
+
+    // Offspring near the edge and cursor or API spawns can land outside the world.
+    fn place_in_world(mut pos: RowVector3<f32>, world_dim: &WorldDimensions, boundary: &BoundaryMode) -> RowVector3<f32> {
+        match boundary {
+            BoundaryMode::Wrap => {
+                BoidSystem::wrap_position(&mut pos, world_dim);
+                pos
+            },
+            BoundaryMode::Steer => world_dim.clamp(pos),
+        }
+    }
+}
+
+impl<'s> System<'s> for LifecycleSystem {
+    type SystemData = (WriteStorage<'s, Boid>,
+                       WriteStorage<'s, Transform>,
+                       WriteStorage<'s, Energy>,
+                       WriteStorage<'s, Age>,
+                       WriteStorage<'s, SpriteRender>,
+                       WriteStorage<'s, Wander>,
+                       Write<'s, BoidInfo>,
+                       Read<'s, WorldDimensions>,
+                       Read<'s, BoundaryMode>,
+                       Write<'s, SpawnRequests>,
+                       Option<Read<'s, BoidSprite>>,
+                       Read<'s, SpeciesList>,
+                       Read<'s, PopulationConfig>,
+                       Read<'s, EnergyConfig>,
+                       Read<'s, WanderConfig>,
+                       Write<'s, WanderSeeder>,
+                       Write<'s, SimRng>,
+                       Write<'s, BoidIds>,
+                       Option<Write<'s, DeathLog>>,
+                       Read<'s, Time>,
+                       Entities<'s>);
+
+    fn run(&mut self, (mut boids, mut transforms, mut energies, mut ages, mut sprites, mut wanders, mut boid_info, world_dim, boundary, mut requests, boid_sprite, species_list, population, energy_config, wander_config, mut seeder, mut sim_rng, mut ids, mut death_log, time, entities): Self::SystemData) {
+        let rng = &mut sim_rng.0;
+        // Deletes only land at the end of the frame, so boids starved or eaten earlier this frame
+        // are told apart by having already left the spatial index.
+        let mut live: HashSet<Entity> = boid_info.qt.boids().iter()
+            .filter_map(Boid::get_entity)
+            .filter(|entity| entities.is_alive(*entity))
+            .collect();
+        for (entity, boid, age) in (&entities, &boids, &mut ages).join() {
+            if !live.contains(&entity) {
+                continue;
+            }
+            age.advance(time.delta_seconds());
+            let too_old = population.max_age.map_or(false, |max_age| age.get_seconds() > max_age);
+            if too_old {
+                if let Some(log) = death_log.as_mut() {
+                    log.0.push(DeathRecord::new(boid, Some(age), energies.get(entity)));
+                }
+                boid_info.qt.remove(boid);
+                if let Err(e) = entities.delete(entity) {
+                    println!("Failed to despawn boid: {}", e);
+                }
+                live.remove(&entity);
+            }
+        }
+        let alive = live.len();
+
+        let mut births = vec![];
+        for (entity, boid, energy) in (&entities, &boids, &mut energies).join() {
+            if alive + births.len() >= population.max_population {
+                break;
+            }
+            if !live.contains(&entity) || energy.is_depleted() || energy.fraction() < population.birth_threshold {
+                continue;
+            }
+
+            let share = energy.get_value() * population.offspring_share;
+            energy.consume(share);
+            let mut child = Boid::new(Self::offspring_position(rng, boid, population.spawn_radius), ids.next());
+            child.set_velocity(boid.get_velocity());
+            child.set_species(boid.get_species());
+            child.set_genome(boid.get_genome().mutate(rng, population.mutation_rate, population.mutation_strength));
+            births.push(Birth {
+                boid: child,
+                energy: Some(Energy::new(share, energy.get_max())),
+                sprite: sprites.get(entity).cloned(),
+            });
+        }
+
+        let room = population.max_population.saturating_sub(alive + births.len());
+        if requests.0.len() > room {
+            println!("Population cap of {} reached, dropping {} spawn requests", population.max_population, requests.0.len() - room);
+            requests.0.truncate(room);
+        }
+        for request in requests.0.drain(..) {
+            let mut boid = Boid::new(request.position, ids.next());
+            boid.set_velocity(request.velocity);
+            boid.set_species(request.species);
+            if let Some(species) = species_list.0.get(request.species) {
+                boid.set_genome(species.genome.clone());
+            }
+            births.push(Birth {
+                boid,
+                energy: if energy_config.enabled {
+                    Some(Energy::new(energy_config.initial, energy_config.max))
+                }
+                else {
+                    None
+                },
+                sprite: boid_sprite.as_ref().map(|sheet| SpriteRender {
+                    sprite_sheet: sheet.0.clone(),
+                    sprite_number: 0,
+                }),
+            });
+        }
+
+        for birth in births {
+            let Birth { mut boid, energy, sprite } = birth;
+            boid.set_position(Self::place_in_world(boid.get_position(), &world_dim, &boundary));
+            let entity = entities.create();
+            boid.set_entity(entity);
+            if !boid_info.qt.insert(boid.clone()) {
+                println!("Failed to add boid at {} to the spatial index", boid.get_position());
+                entities.delete(entity).ok();
+                continue;
+            }
+
+            let mut transform = Transform::default();
+            let scale = species_list.0.get(boid.get_species())
+                .map_or((0.025, 0.035), |species| species.scale);
+            transform.set_translation(boid.get_position().transpose());
+            transform.set_scale(Vector3::new(scale.0, scale.1, 0.0));
+
+            let inserted = boids.insert(entity, boid)
+                .and(transforms.insert(entity, transform))
+                .and(ages.insert(entity, Age::default()));
+            if let Err(e) = inserted {
+                println!("Failed to spawn boid: {}", e);
+            }
+            if let Some(energy) = energy {
+                energies.insert(entity, energy).ok();
+            }
+            if let Some(sprite) = sprite {
+                sprites.insert(entity, sprite).ok();
+            }
+            if wander_config.enabled {
+                wanders.insert(entity, Wander::spawn(&mut seeder.0)).ok();
+            }
+        }
+    }
+}
@@ -6,16 +6,86 @@ mod boid_system;
 mod resources;
 mod types;
 mod camera_system;
+mod paused;
+mod scenario;
+mod cli;
+mod energy;
+mod food;
+mod energy_system;
+mod food_system;
+mod age;
+mod lifecycle_system;
+mod genome;
+mod predation_system;
+mod evolution;
+mod steering;
+mod behaviors;
+mod wander;
+mod vicsek_system;
+mod metrics;
+mod metrics_system;
+mod flock;
+mod cluster_system;
+mod clock_system;
+mod recording;
+mod recorder_system;
+mod replay;
+mod snapshot;
+mod export;
+mod export_system;
+mod columnar;
+mod columnar_system;
+mod headless;
+mod sweep;
+mod api;
+mod api_system;
+mod script;
+mod script_system;
+mod interaction_system;
+mod inspector;
+mod inspector_system;
+mod debug_system;
 
 use simulator::Simulator;
 use boid_system::BoidSystem;
+use vicsek_system::VicsekSystem;
 use weather::WeatherSystem;
 use camera_system::CameraSystem;
-use resources::CurrentState;
+use energy_system::EnergySystem;
+use food_system::FoodSystem;
+use lifecycle_system::LifecycleSystem;
+use metrics_system::MetricsSystem;
+use cluster_system::ClusterSystem;
+use clock_system::ClockSystem;
+use recorder_system::RecorderSystem;
+use recording::Recording;
+use replay::ReplayState;
+use snapshot::Snapshot;
+use export_system::ExportSystem;
+use columnar_system::ColumnarSystem;
+use headless::HeadlessState;
+use sweep::SweepConfig;
+use api_system::ApiSystem;
+use script_system::ScriptSystem;
+use interaction_system::InteractionSystem;
+use inspector_system::InspectorSystem;
+use debug_system::DebugSystem;
+use predation_system::PredationSystem;
+use evolution::{
+    EvolutionState,
+    EvolutionConfig,
+};
+use resources::{
+    CurrentState,
+    WorldConfig,
+};
+use scenario::Scenario;
+use cli::Args;
 use amethyst::{
     prelude::*,
     core::{
         transform::TransformBundle,
+        frame_limiter::FrameRateLimitStrategy,
     },
     input::{
         InputBundle,
@@ -23,15 +93,39 @@ use amethyst::{
     },
     renderer::{
         plugins::{
+            RenderDebugLines,
             RenderFlat2D,
             RenderToWindow,
         },
         types::DefaultBackend,
         RenderingBundle,
     },
+    ui::{
+        RenderUi,
+        UiBundle,
+    },
     utils::application_root_dir,
+    config::Config,
 };
 
+fn with_simulation_systems<'a, 'b>(builder: GameDataBuilder<'a, 'b>) -> GameDataBuilder<'a, 'b> {
+    builder
+        .with(ClockSystem.pausable(CurrentState::Running), "clock_system", &[])
+        .with(ScriptSystem.pausable(CurrentState::Running), "script_system", &["clock_system"])
+        .with(WeatherSystem::default().pausable(CurrentState::Running), "weather_system", &[])
+        .with(BoidSystem.pausable(CurrentState::Running), "boid_system", &["clock_system"])
+        .with(VicsekSystem.pausable(CurrentState::Running), "vicsek_system", &["boid_system"])
+        .with(PredationSystem.pausable(CurrentState::Running), "predation_system", &["vicsek_system"])
+        .with(EnergySystem.pausable(CurrentState::Running), "energy_system", &["predation_system"])
+        .with(FoodSystem.pausable(CurrentState::Running), "food_system", &["energy_system"])
+        .with(LifecycleSystem.pausable(CurrentState::Running), "lifecycle_system", &["energy_system"])
+        .with(MetricsSystem.pausable(CurrentState::Running), "metrics_system", &["lifecycle_system"])
+        .with(ClusterSystem.pausable(CurrentState::Running), "cluster_system", &["lifecycle_system"])
+        .with(RecorderSystem.pausable(CurrentState::Running), "recorder_system", &["metrics_system", "cluster_system"])
+        .with(ExportSystem.pausable(CurrentState::Running), "export_system", &["metrics_system", "cluster_system"])
+        .with(ColumnarSystem.pausable(CurrentState::Running), "columnar_system", &["metrics_system", "cluster_system"])
+        .with(ApiSystem, "api_system", &["script_system", "metrics_system", "cluster_system"])
+}
 
 fn main() -> amethyst::Result<()> {
     //let mut sim = BoidsSim::new();
@@ -43,19 +137,94 @@ fn main() -> amethyst::Result<()> {
         .join("display.ron");
     let asset_dir = app_root.join("assets");
     let bindings = app_root.join("config").join("bindings.ron");
+    let world_config = app_root.join("config").join("world.ron");
+    let args = Args::parse().map_err(amethyst::Error::from_string)?;
+    if let Some(path) = args.sweep {
+        let config = SweepConfig::load_no_fallback(path)?;
+        return sweep::run_sweep(&config).map_err(|e| amethyst::Error::from_string(format!("Sweep failed: {}", e)))
+    }
+    let scenario = match args.scenario {
+        Some(path) => Scenario::load_no_fallback(path)?,
+        None => Scenario::load(app_root.join("scenarios").join("default.ron")),
+    };
+    let config = scenario.world.clone().unwrap_or_else(|| WorldConfig::load(&world_config));
+    scenario.validate(&config).map_err(amethyst::Error::from_string)?;
+
+    if let Some(path) = args.evolve {
+        let evolution_config = EvolutionConfig::load_no_fallback(path)?;
+        let script = scenario.load_script().map_err(amethyst::Error::from_string)?;
+        let game_data = with_simulation_systems(GameDataBuilder::default()
+            .with_bundle(TransformBundle::new())?);
+        let mut evolution = Application::build(asset_dir, EvolutionState::new(evolution_config, scenario, config).with_script(script))?
+            .with_frame_limit(FrameRateLimitStrategy::Unlimited, 0)
+            .build(game_data)?;
+        evolution.run();
+        return Ok(())
+    }
+
+    if let Some(duration) = args.headless {
+        let time_step = args.time_step.unwrap_or(1.0 / 60.0);
+        let game_data = with_simulation_systems(GameDataBuilder::default()
+            .with_bundle(TransformBundle::new())?);
+        // With the API attached someone is watching, so run in real time instead of flat out.
+        let (strategy, fps) = match args.api {
+            Some(_) => (FrameRateLimitStrategy::Sleep, (1.0 / time_step).round() as u32),
+            None => (FrameRateLimitStrategy::Unlimited, 0),
+        };
+        let script = scenario.load_script().map_err(amethyst::Error::from_string)?;
+        let state = HeadlessState::new(scenario, config, duration, time_step, args.summary)
+            .with_script(script)
+            .with_api(args.api, if args.api_interval > 0 { args.api_interval } else { 6 });
+        let mut headless = Application::build(asset_dir, state)?
+            .with_frame_limit(strategy, fps)
+            .build(game_data)?;
+        headless.run();
+        return Ok(())
+    }
+
     let input_bundle = InputBundle::<StringBindings>::new().with_bindings_from_file(bindings)?;
     let game_data = GameDataBuilder::default()
         .with_bundle(RenderingBundle::<DefaultBackend>::new()
                      .with_plugin(RenderToWindow::from_config_path(display_config)
                                   .with_clear([0.0, 0.0, 0.0, 1.0]))
-                     .with_plugin(RenderFlat2D::default()))?
+                     .with_plugin(RenderFlat2D::default())
+                     .with_plugin(RenderDebugLines::default())
+                     .with_plugin(RenderUi::default()))?
         .with_bundle(TransformBundle::new())?
         .with_bundle(input_bundle)?
-        .with(CameraSystem, "camera_system", &["input_system"])
-        .with(WeatherSystem::default().pausable(CurrentState::Running), "weather_system", &[])
-        .with(BoidSystem, "boid_system", &[]);
+        .with_bundle(UiBundle::<StringBindings>::new())?
+        .with(CameraSystem, "camera_system", &["input_system"]);
+
+    if let Some(path) = args.replay {
+        let recording = Recording::load(&path)
+            .map_err(|e| amethyst::Error::from_string(format!("Failed to load recording {}: {}", path.display(), e)))?;
+        let mut replay = Application::new(asset_dir, ReplayState::new(recording), game_data)?;
+        replay.run();
+        return Ok(())
+    }
+
+    let snapshot = match args.load {
+        Some(path) => Some(Snapshot::load(&path)
+            .map_err(|e| amethyst::Error::from_string(format!("Failed to load snapshot {}: {}", path.display(), e)))?),
+        None => None,
+    };
+    let script = snapshot.as_ref().map_or(&scenario, |snapshot| &snapshot.scenario).load_script()
+        .map_err(amethyst::Error::from_string)?;
+    let mut simulator = Simulator::new(scenario, world_config)
+        .with_script(script)
+        .with_recording(args.record)
+        .with_export(args.export, args.export_interval, args.export_ids)
+        .with_parquet(args.parquet, if args.parquet_batch > 0 { args.parquet_batch } else { 100 })
+        .with_api(args.api, if args.api_interval > 0 { args.api_interval } else { 6 });
+    if let Some(snapshot) = snapshot {
+        simulator = simulator.with_snapshot(snapshot);
+    }
     let _world = World::new();
-    let mut sim = Application::new(asset_dir, Simulator, game_data)?;
+    let game_data = with_simulation_systems(game_data)
+        .with(InteractionSystem.pausable(CurrentState::Running), "interaction_system", &["boid_system"])
+        .with(InspectorSystem, "inspector_system", &["boid_system", "cluster_system"])
+        .with(DebugSystem, "debug_system", &["boid_system"]);
+    let mut sim = Application::new(asset_dir, simulator, game_data)?;
     sim.run();
     Ok(())
 }
@@ -0,0 +1,247 @@
+use std::fmt;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::core::math::RowVector3;
+use crate::{
+    boid::Boid,
+    quadtree::QuadTree,
+    resources::WorldDimensions,
+};
+
+pub const SPEED_BINS: usize = 10;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpeedDistribution {
+    pub mean: f32,
+    pub std_dev: f32,
+    pub min: f32,
+    pub max: f32,
+    pub histogram: Vec<usize>,
+}
+
+impl SpeedDistribution {
+    pub fn new(speeds: &[f32]) -> Self {
+        if speeds.is_empty() {
+            return SpeedDistribution::default()
+        }
+
+        let n = speeds.len() as f32;
+        let mean = speeds.iter().sum::<f32>() / n;
+        let variance = speeds.iter().map(|speed| (speed - mean).powi(2)).sum::<f32>() / n;
+        let min = speeds.iter().cloned().fold(std::f32::INFINITY, f32::min);
+        let max = speeds.iter().cloned().fold(0.0, f32::max);
+
+        let mut histogram = vec![0; SPEED_BINS];
+        let width = (max - min) / SPEED_BINS as f32;
+        for speed in speeds {
+            let bin = if width > 0.0 {
+                (((speed - min) / width) as usize).min(SPEED_BINS - 1)
+            }
+            else {
+                0
+            };
+            histogram[bin] += 1;
+        }
+
+        SpeedDistribution {
+            mean,
+            std_dev: variance.sqrt(),
+            min,
+            max,
+            histogram,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metrics {
+    pub tick: u64,
+    pub time: f64,
+    pub population: usize,
+    pub polarization: f32,
+    pub milling: f32,
+    pub nearest_neighbour: f32,
+    pub flock_radius: f32,
+    pub speed: SpeedDistribution,
+}
+
+impl Metrics {
+    // On a wrapping world the centroid is the circular mean along each axis and
+    // offsets use the nearest periodic image, so a flock straddling an edge stays whole.
+    fn centroid(boids: &[Boid], torus: Option<&WorldDimensions>) -> RowVector3<f32> {
+        let n = boids.len() as f32;
+        let world_dim = match torus {
+            Some(world_dim) => world_dim,
+            None => return boids.iter().fold(RowVector3::zeros(), |acc, boid| acc + boid.get_position()) / n,
+        };
+        let mean = |value: &dyn Fn(&Boid) -> f32, min: f32, max: f32| {
+            let period = max - min;
+            let (sin, cos) = boids.iter()
+                .map(|boid| 2.0 * std::f32::consts::PI * (value(boid) - min) / period)
+                .fold((0.0, 0.0), |(sin, cos), angle: f32| (sin + angle.sin(), cos + angle.cos()));
+            min + (sin.atan2(cos) / (2.0 * std::f32::consts::PI) * period).rem_euclid(period)
+        };
+        RowVector3::new(mean(&|boid| boid.get_position()[0], world_dim.min_x, world_dim.max_x),
+                        mean(&|boid| boid.get_position()[1], world_dim.min_y, world_dim.max_y),
+                        0.0)
+    }
+
+    fn offset(from: RowVector3<f32>, to: RowVector3<f32>, torus: Option<&WorldDimensions>) -> RowVector3<f32> {
+        let mut offset = to - from;
+        if let Some(world_dim) = torus {
+            let width = world_dim.max_x - world_dim.min_x;
+            let height = world_dim.max_y - world_dim.min_y;
+            offset[0] -= width * (offset[0] / width).round();
+            offset[1] -= height * (offset[1] / height).round();
+        }
+        offset
+    }
+
+    pub fn compute(boids: &[Boid], qt: &mut QuadTree, torus: Option<&WorldDimensions>) -> Self {
+        let n = boids.len();
+        if n == 0 {
+            return Metrics::default()
+        }
+
+        let centroid = Self::centroid(boids, torus);
+        let mut heading_sum = RowVector3::<f32>::zeros();
+        let mut momentum = 0.0;
+        let mut momentum_norm = 0.0;
+        let mut radius_sq = 0.0;
+        let mut speeds = Vec::with_capacity(n);
+        for boid in boids {
+            let vel = boid.get_velocity();
+            let offset = Self::offset(centroid, boid.get_position(), torus);
+            let speed = vel.magnitude();
+            if speed > 0.0 {
+                heading_sum += vel.unscale(speed);
+            }
+            momentum += offset[0] * vel[1] - offset[1] * vel[0];
+            momentum_norm += offset.magnitude() * speed;
+            radius_sq += offset.magnitude_squared();
+            speeds.push(speed);
+        }
+
+        let distances: Vec<f32> = boids.iter()
+            .filter_map(|boid| Self::nearest_distance(boid, qt, torus))
+            .collect();
+
+        Metrics {
+            tick: 0,
+            time: 0.0,
+            population: n,
+            polarization: heading_sum.magnitude() / n as f32,
+            milling: if momentum_norm > 0.0 { momentum.abs() / momentum_norm } else { 0.0 },
+            nearest_neighbour: if distances.is_empty() { 0.0 } else { distances.iter().sum::<f32>() / distances.len() as f32 },
+            flock_radius: (radius_sq / n as f32).sqrt(),
+            speed: SpeedDistribution::new(&speeds),
+        }
+    }
+
+    fn nearest_distance(boid: &Boid, qt: &mut QuadTree, torus: Option<&WorldDimensions>) -> Option<f32> {
+        let pos = boid.get_position();
+        // On a wrapping world the nearest boid may sit across an edge, so search its periodic images too.
+        let images: Vec<RowVector3<f32>> = match torus {
+            Some(world_dim) => {
+                let (width, height) = (world_dim.max_x - world_dim.min_x, world_dim.max_y - world_dim.min_y);
+                [-1.0, 0.0, 1.0].iter()
+                    .flat_map(|dx: &f32| [-1.0, 0.0, 1.0].iter().map(move |dy: &f32| RowVector3::new(dx * width, dy * height, 0.0)))
+                    .collect()
+            },
+            None => vec![RowVector3::zeros()],
+        };
+        let mut radius = 50.0;
+        while radius < 1.0e5 {
+            let nearest = images.iter()
+                .flat_map(|image| {
+                    let center = pos + image;
+                    qt.search_range(center[0] - radius..center[0] + radius, center[1] - radius..center[1] + radius)
+                })
+                .filter(|other| other.get_entity() != boid.get_entity())
+                .map(|other| Self::offset(pos, other.get_position(), torus).magnitude())
+                .fold(std::f32::INFINITY, f32::min);
+            if nearest <= radius {
+                return Some(nearest)
+            }
+            radius *= 2.0;
+        }
+        None
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tick {} t={:.1}s n={} polarization={:.3} milling={:.3} nn={:.1} radius={:.1} speed={:.1}±{:.1}",
+               self.tick, self.time, self.population, self.polarization, self.milling,
+               self.nearest_neighbour, self.flock_radius, self.speed.mean, self.speed.std_dev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst::ecs::prelude::{
+        Builder,
+        World,
+        WorldExt,
+    };
+    use crate::resources::WorldConfig;
+    use super::*;
+
+    fn compute(states: &[((f32, f32), (f32, f32))], torus: Option<&WorldDimensions>) -> Metrics {
+        let mut world = World::new();
+        let mut qt = QuadTree::new(0.0..100.0, 0.0..100.0);
+        let boids: Vec<Boid> = states.iter()
+            .map(|((x, y), (vx, vy))| {
+                let mut boid = Boid::new(RowVector3::new(*x, *y, 0.0), 0);
+                boid.set_velocity(RowVector3::new(*vx, *vy, 0.0));
+                boid.set_entity(world.create_entity().build());
+                assert!(qt.insert(boid.clone()));
+                boid
+            })
+            .collect();
+        Metrics::compute(&boids, &mut qt, torus)
+    }
+
+    fn torus() -> WorldDimensions {
+        WorldDimensions::from_config(&WorldConfig { width: 100.0, height: 100.0, region_size: 100.0 })
+    }
+
+    #[test]
+    fn aligned_flock_is_polarized() {
+        let metrics = compute(&[((10.0, 10.0), (1.0, 0.0)), ((20.0, 10.0), (2.0, 0.0)), ((30.0, 10.0), (3.0, 0.0))], None);
+        assert_eq!(metrics.population, 3);
+        assert!((metrics.polarization - 1.0).abs() < 1e-5);
+        assert!((metrics.nearest_neighbour - 10.0).abs() < 1e-4);
+        assert!((metrics.speed.mean - 2.0).abs() < 1e-5);
+        assert_eq!((metrics.speed.min, metrics.speed.max), (1.0, 3.0));
+    }
+
+    #[test]
+    fn rotating_ring_mills() {
+        let metrics = compute(&[((60.0, 50.0), (0.0, 1.0)), ((50.0, 60.0), (-1.0, 0.0)), ((40.0, 50.0), (0.0, -1.0)), ((50.0, 40.0), (1.0, 0.0))], None);
+        assert!(metrics.polarization < 1e-5);
+        assert!((metrics.milling - 1.0).abs() < 1e-5);
+        assert!((metrics.flock_radius - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn wrapping_world_measures_across_the_edge() {
+        let states = [((1.0, 50.0), (1.0, 0.0)), ((99.0, 50.0), (1.0, 0.0))];
+        let plain = compute(&states, None);
+        assert!((plain.nearest_neighbour - 98.0).abs() < 1e-3);
+        assert!((plain.flock_radius - 49.0).abs() < 1e-3);
+
+        let wrapped = compute(&states, Some(&torus()));
+        assert!((wrapped.nearest_neighbour - 2.0).abs() < 1e-3);
+        assert!((wrapped.flock_radius - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn empty_flock_has_default_metrics() {
+        let metrics = compute(&[], None);
+        assert_eq!(metrics.population, 0);
+        assert_eq!(metrics.polarization, 0.0);
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashSet;
+use amethyst::{
+    prelude::*,
+    core::SystemDesc,
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        Entities,
+        Entity,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    metrics::Metrics,
+    resources::{
+        BoidInfo,
+        SimClock,
+        WorldDimensions,
+    },
+    scenario::BoundaryMode,
+};
+
+#[derive(SystemDesc)]
+pub struct MetricsSystem;
+impl<'s> System<'s> for MetricsSystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       Write<'s, BoidInfo>,
+                       Write<'s, Metrics>,
+                       Read<'s, SimClock>,
+                       Read<'s, WorldDimensions>,
+                       Read<'s, BoundaryMode>,
+                       Entities<'s>);
+
+    fn run(&mut self, (boids, mut boid_info, mut metrics, clock, world_dim, boundary, entities): Self::SystemData) {
+        // Deleted entities keep their components until the frame ends, but every despawn
+        // also takes the boid out of the quadtree.
+        let live: HashSet<Entity> = boid_info.qt.boids().iter().filter_map(Boid::get_entity).collect();
+        let boids: Vec<Boid> = (&entities, &boids).join()
+            .filter(|(entity, _)| live.contains(entity))
+            .map(|(_, boid)| boid.clone())
+            .collect();
+        let torus = if *boundary == BoundaryMode::Wrap { Some(&*world_dim) } else { None };
+        *metrics = Metrics::compute(&boids, &mut boid_info.qt, torus);
+        metrics.tick = clock.tick;
+        metrics.time = clock.seconds;
+    }
+}
@@ -0,0 +1,60 @@
+use amethyst::{
+    prelude::*,
+    input::{
+        is_close_requested,
+        InputEvent,
+    },
+};
+use crate::{
+    resources::CurrentState,
+    simulator::{
+        Simulator,
+        adjust_time_scale,
+    },
+    api::ApiServer,
+};
+
+#[derive(Default)]
+pub struct Paused {
+    step: bool,
+}
+
+impl SimpleState for Paused {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        *data.world.write_resource::<CurrentState>() = CurrentState::Paused;
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match event {
+            StateEvent::Window(event) if is_close_requested(&event) => return Trans::Quit,
+            StateEvent::Input(InputEvent::ActionPressed(action)) => {
+                match action.as_str() {
+                    "pause" => return Trans::Pop,
+                    "step" => self.step = true,
+                    "select" => Simulator::select_at_cursor(data.world),
+                    "debug_overlay" => Simulator::toggle_debug_overlay(data.world),
+                    _ => adjust_time_scale(data.world, &action),
+                }
+            },
+            _ => {},
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let request = data.world.try_fetch_mut::<ApiServer>().and_then(|mut server| server.take_pause_request());
+        if request == Some(false) {
+            return Trans::Pop
+        }
+        // States update before the dispatcher runs, so flipping to Running here
+        // lets the pausable systems run for exactly this one frame.
+        *data.world.write_resource::<CurrentState>() = if self.step {
+            self.step = false;
+            CurrentState::Running
+        }
+        else {
+            CurrentState::Paused
+        };
+        Trans::None
+    }
+}
@@ -0,0 +1,80 @@
+use amethyst::{
+    prelude::*,
+    core::SystemDesc,
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        WriteStorage,
+        Entities,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    age::Age,
+    energy::Energy,
+    resources::{
+        BoidInfo,
+        SpeciesList,
+        DeathLog,
+        DeathRecord,
+    },
+};
+
+#[derive(SystemDesc)]
+pub struct PredationSystem;
+impl<'s> System<'s> for PredationSystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       ReadStorage<'s, Age>,
+                       WriteStorage<'s, Energy>,
+                       Write<'s, BoidInfo>,
+                       Read<'s, SpeciesList>,
+                       Option<Write<'s, DeathLog>>,
+                       Entities<'s>);
+
+    fn run(&mut self, (boids, ages, mut energies, mut boid_info, species_list, mut death_log, entities): Self::SystemData) {
+        if !species_list.has_predators() {
+            return
+        }
+
+        let mut caught = vec![];
+        let mut meals = vec![];
+        for (entity, boid) in (&entities, &boids).join().filter(|(_, boid)| species_list.is_predator(boid.get_species())) {
+            let radius = species_list.0[boid.get_species()].catch_radius;
+            let pos = boid.get_position();
+            let nearby = boid_info.qt.search_range(pos[0] - radius..pos[0] + radius, pos[1] - radius..pos[1] + radius);
+            for prey in nearby {
+                let is_prey = !species_list.is_predator(prey.get_species());
+                if is_prey && (prey.get_position() - pos).magnitude() < radius && !caught.contains(&prey.get_entity()) {
+                    caught.push(prey.get_entity());
+                    meals.push(entity);
+                }
+            }
+        }
+
+        for entity in caught.into_iter().flatten() {
+            let boid = match boids.get(entity) {
+                Some(boid) => boid,
+                None => continue,
+            };
+            if let Some(log) = death_log.as_mut() {
+                log.0.push(DeathRecord::new(boid, ages.get(entity), energies.get(entity)));
+            }
+            boid_info.qt.remove(boid);
+            if let Err(e) = entities.delete(entity) {
+                println!("Failed to despawn caught boid: {}", e);
+            }
+        }
+
+        for predator in meals {
+            if let Some(energy) = energies.get_mut(predator) {
+                let meal = energy.get_max() * 0.5;
+                energy.replenish(meal);
+            }
+        }
+    }
+}
@@ -74,9 +74,50 @@ impl QuadTree {
         false
     }
 
+    pub fn remove(&mut self, boid: &Boid) -> bool {
+        let pos = boid.get_position();
+        if !(self.x_range.contains(&pos[0]) && self.y_range.contains(&pos[1])) {
+            return false
+        }
+
+        let len = self.boids.len();
+        self.boids.retain(|other| other.get_entity() != boid.get_entity());
+        if self.boids.len() != len {
+            return true
+        }
+
+        if self.northwest == None {
+            return false
+        }
+        self.northwest.as_mut().unwrap().remove(boid)
+            || self.northeast.as_mut().unwrap().remove(boid)
+            || self.southwest.as_mut().unwrap().remove(boid)
+            || self.southeast.as_mut().unwrap().remove(boid)
+    }
+
+    pub fn boids(&self) -> Vec<Boid> {
+        let mut boids = self.boids.clone();
+        for child in [&self.northwest, &self.northeast, &self.southwest, &self.southeast].iter() {
+            if let Some(child) = child {
+                boids.append(&mut child.boids());
+            }
+        }
+        boids
+    }
+
+    pub fn nodes(&self) -> Vec<(Range<f32>, Range<f32>)> {
+        let mut nodes = vec![(self.x_range.clone(), self.y_range.clone())];
+        for child in [&self.northwest, &self.northeast, &self.southwest, &self.southeast].iter() {
+            if let Some(child) = child {
+                nodes.append(&mut child.nodes());
+            }
+        }
+        nodes
+    }
+
     pub fn search_range(&mut self, x_range: Range<f32>, y_range: Range<f32>) -> Vec<Boid> {
         let mut results = vec![];
-        let intersect = self.x_range.start < x_range.end || self.y_range.start < y_range.end || self.x_range.end > x_range.start || self.y_range.end > y_range.start;
+        let intersect = self.x_range.start < x_range.end && self.y_range.start < y_range.end && self.x_range.end > x_range.start && self.y_range.end > y_range.start;
         
         if !intersect {
             return results
@@ -137,3 +178,82 @@ impl QuadTree {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use amethyst::{
+        core::math::RowVector3,
+        ecs::prelude::{
+            Builder,
+            World,
+            WorldExt,
+        },
+    };
+    use super::*;
+
+    fn boids(world: &mut World, positions: &[(f32, f32)]) -> Vec<Boid> {
+        positions.iter()
+            .map(|(x, y)| {
+                let mut boid = Boid::new(RowVector3::new(*x, *y, 0.0), 0);
+                boid.set_entity(world.create_entity().build());
+                boid
+            })
+            .collect()
+    }
+
+    fn filled(world: &mut World, positions: &[(f32, f32)]) -> (QuadTree, Vec<Boid>) {
+        let mut qt = QuadTree::new(0.0..100.0, 0.0..100.0);
+        let boids = boids(world, positions);
+        for boid in &boids {
+            assert!(qt.insert(boid.clone()));
+        }
+        (qt, boids)
+    }
+
+    #[test]
+    fn rejects_positions_outside_half_open_ranges() {
+        let mut world = World::new();
+        let mut qt = QuadTree::new(0.0..100.0, 0.0..100.0);
+        for boid in boids(&mut world, &[(100.0, 50.0), (50.0, 100.0), (-0.1, 50.0)]) {
+            assert!(!qt.insert(boid));
+        }
+        for boid in boids(&mut world, &[(0.0, 0.0), (99.9, 99.9)]) {
+            assert!(qt.insert(boid));
+        }
+    }
+
+    #[test]
+    fn search_range_excludes_range_end() {
+        let mut world = World::new();
+        let (mut qt, _) = filled(&mut world, &[(10.0, 10.0), (20.0, 10.0), (30.0, 10.0)]);
+        assert_eq!(qt.search_range(10.0..20.0, 0.0..100.0).len(), 1);
+        assert_eq!(qt.search_range(10.0..20.1, 0.0..100.0).len(), 2);
+        assert_eq!(qt.search_range(-50.0..0.0, 0.0..100.0).len(), 0);
+    }
+
+    #[test]
+    fn search_range_finds_boids_across_subdivisions() {
+        let mut world = World::new();
+        let positions: Vec<(f32, f32)> = (0..10).flat_map(|i| (0..10).map(move |j| (i as f32 * 10.0 + 5.0, j as f32 * 10.0 + 5.0))).collect();
+        let (mut qt, _) = filled(&mut world, &positions);
+        assert_eq!(qt.search_range(0.0..100.0, 0.0..100.0).len(), 100);
+        // Straddles the first split at 50 on both axes.
+        assert_eq!(qt.search_range(40.0..60.0, 40.0..60.0).len(), 4);
+        assert_eq!(qt.search_range(95.0..200.0, 95.0..200.0).len(), 1);
+    }
+
+    #[test]
+    fn insert_remove_round_trip() {
+        let mut world = World::new();
+        let positions: Vec<(f32, f32)> = (0..20).map(|i| (i as f32 * 4.9, 100.0 - i as f32 * 4.9 - 0.1)).collect();
+        let (mut qt, boids) = filled(&mut world, &positions);
+        assert_eq!(qt.boids().len(), boids.len());
+        for (removed, boid) in boids.iter().enumerate() {
+            assert!(qt.remove(boid));
+            assert!(!qt.remove(boid));
+            assert_eq!(qt.boids().len(), boids.len() - removed - 1);
+        }
+        assert!(qt.insert(boids[3].clone()));
+        assert_eq!(qt.boids(), vec![boids[3].clone()]);
+    }
+}
@@ -0,0 +1,54 @@
+use amethyst::{
+    prelude::*,
+    core::SystemDesc,
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        ReadStorage,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    flock::Flocks,
+    recording::{
+        Recorder,
+        BoidState,
+    },
+    resources::{
+        Weather,
+        SimClock,
+    },
+};
+
+const FLUSH_INTERVAL: u64 = 60;
+
+#[derive(SystemDesc)]
+pub struct RecorderSystem;
+impl<'s> System<'s> for RecorderSystem {
+    type SystemData = (ReadStorage<'s, Boid>,
+                       Option<Write<'s, Recorder>>,
+                       Read<'s, Weather>,
+                       Read<'s, Flocks>,
+                       Read<'s, SimClock>);
+
+    fn run(&mut self, (boids, recorder, weather, flocks, clock): Self::SystemData) {
+        let mut recorder = match recorder {
+            Some(recorder) => recorder,
+            None => return,
+        };
+
+        let states = boids.join().map(BoidState::new).collect();
+        if let Err(e) = recorder.record(clock.tick, clock.seconds, states, weather.get_weather(), &flocks) {
+            println!("Failed to record frame {}: {}", clock.tick, e);
+        }
+        if clock.tick % FLUSH_INTERVAL == 0 {
+            if let Err(e) = recorder.flush() {
+                println!("Failed to flush recording: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+        ErrorKind,
+        Write,
+    },
+    path::Path,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use crate::{
+    boid::Boid,
+    resources::{
+        WorldConfig,
+        WeatherType,
+    },
+    scenario::Species,
+    flock::{
+        FlockEvent,
+        Flocks,
+    },
+};
+
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoidState {
+    pub id: usize,
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub species: usize,
+}
+
+impl BoidState {
+    pub fn new(boid: &Boid) -> Self {
+        let pos = boid.get_position();
+        let vel = boid.get_velocity();
+        BoidState {
+            id: boid.id,
+            position: [pos[0], pos[1], pos[2]],
+            velocity: [vel[0], vel[1], vel[2]],
+            species: boid.get_species(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WorldEvent {
+    Spawned(Vec<usize>),
+    Died(Vec<usize>),
+    Weather(WeatherType),
+    Flock(FlockEvent),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub tick: u64,
+    pub time: f64,
+    pub boids: Vec<BoidState>,
+    pub events: Vec<WorldEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub version: u32,
+    pub world: WorldConfig,
+    pub species: Vec<Species>,
+}
+
+pub struct Recorder {
+    writer: BufWriter<File>,
+    last_ids: Vec<usize>,
+    last_weather: Option<WeatherType>,
+    flock_events: u64,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P, world: &WorldConfig, species: &[Species]) -> Result<Self, bincode::Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = RecordingHeader {
+            version: FORMAT_VERSION,
+            world: world.clone(),
+            species: species.to_vec(),
+        };
+        bincode::serialize_into(&mut writer, &header)?;
+        Ok(Recorder {
+            writer,
+            last_ids: vec![],
+            last_weather: None,
+            flock_events: 0,
+        })
+    }
+
+    pub fn record(&mut self, tick: u64, time: f64, boids: Vec<BoidState>, weather: &WeatherType, flocks: &Flocks) -> Result<(), bincode::Error> {
+        let mut ids: Vec<usize> = boids.iter().map(|boid| boid.id).collect();
+        ids.sort();
+
+        let mut events = vec![];
+        let spawned: Vec<usize> = ids.iter().filter(|id| self.last_ids.binary_search(id).is_err()).cloned().collect();
+        let died: Vec<usize> = self.last_ids.iter().filter(|id| ids.binary_search(id).is_err()).cloned().collect();
+        if !spawned.is_empty() {
+            events.push(WorldEvent::Spawned(spawned));
+        }
+        if !died.is_empty() {
+            events.push(WorldEvent::Died(died));
+        }
+        if self.last_weather.as_ref() != Some(weather) {
+            events.push(WorldEvent::Weather(weather.clone()));
+            self.last_weather = Some(weather.clone());
+        }
+        events.extend(flocks.events_since(self.flock_events).map(|(_, event)| WorldEvent::Flock(event.clone())));
+        self.flock_events = flocks.event_count();
+        self.last_ids = ids;
+
+        bincode::serialize_into(&mut self.writer, &Frame {
+            tick,
+            time,
+            boids,
+            events,
+        })
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+pub struct Recording {
+    pub header: RecordingHeader,
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, bincode::Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let header: RecordingHeader = bincode::deserialize_from(&mut reader)?;
+        if header.version != FORMAT_VERSION {
+            return Err(Box::new(bincode::ErrorKind::Custom(format!("Unsupported recording version {}", header.version))))
+        }
+
+        let mut frames = vec![];
+        loop {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push(frame),
+                Err(e) => match *e {
+                    bincode::ErrorKind::Io(ref io) if io.kind() == ErrorKind::UnexpectedEof => break,
+                    _ => return Err(e),
+                },
+            }
+        }
+        Ok(Recording {
+            header,
+            frames,
+        })
+    }
+
+    pub fn frame_at(&self, time: f64) -> usize {
+        match self.frames.binary_search_by(|frame| frame.time.partial_cmp(&time).unwrap_or(std::cmp::Ordering::Less)) {
+            Ok(i) => i,
+            Err(i) => i.saturating_sub(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use amethyst::core::math::RowVector3;
+    use super::*;
+
+    fn state(id: usize, x: f32) -> BoidState {
+        let mut boid = Boid::new(RowVector3::new(x, 0.0, 0.0), id);
+        boid.set_velocity(RowVector3::new(1.0, 2.0, 0.0));
+        BoidState::new(&boid)
+    }
+
+    #[test]
+    fn frames_round_trip_with_spawn_and_death_events() {
+        let path = std::env::temp_dir().join(format!("boids-recording-test-{}.bin", std::process::id()));
+        let flocks = Flocks::default();
+        {
+            let mut recorder = Recorder::create(&path, &WorldConfig::default(), &[Species::default()]).unwrap();
+            recorder.record(0, 0.0, vec![state(1, 10.0), state(2, 20.0)], &WeatherType::Sunny(1.0), &flocks).unwrap();
+            // Boid 1 dies and a new boid takes its place; the ids never repeat.
+            recorder.record(1, 0.5, vec![state(2, 21.0), state(3, 10.0)], &WeatherType::Sunny(1.0), &flocks).unwrap();
+            recorder.record(2, 1.0, vec![state(2, 22.0), state(3, 11.0)], &WeatherType::Rainy(0.5), &flocks).unwrap();
+            recorder.flush().unwrap();
+        }
+        let recording = Recording::load(&path);
+        fs::remove_file(&path).ok();
+        let recording = recording.unwrap();
+
+        assert_eq!(recording.header.version, FORMAT_VERSION);
+        assert_eq!(recording.header.species.len(), 1);
+        assert_eq!(recording.frames.len(), 3);
+        assert_eq!(recording.frames[1].boids[1].position, [10.0, 0.0, 0.0]);
+        assert_eq!(recording.frames[1].boids[1].velocity, [1.0, 2.0, 0.0]);
+        assert!(matches!(recording.frames[0].events.as_slice(), [WorldEvent::Spawned(ids), WorldEvent::Weather(_)] if *ids == vec![1, 2]));
+        assert!(matches!(recording.frames[1].events.as_slice(), [WorldEvent::Spawned(spawned), WorldEvent::Died(died)]
+                         if *spawned == vec![3] && *died == vec![1]));
+        assert!(matches!(recording.frames[2].events.as_slice(), [WorldEvent::Weather(WeatherType::Rainy(_))]));
+        assert_eq!(recording.frame_at(0.7), 1);
+        assert_eq!(recording.frame_at(5.0), 2);
+    }
+}
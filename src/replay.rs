@@ -0,0 +1,204 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use amethyst::{
+    prelude::*,
+    core::{
+        transform::Transform,
+        math::Vector3,
+        timing::Time,
+    },
+    input::{
+        is_close_requested,
+        InputEvent,
+    },
+    ecs::prelude::Entity,
+    winit::{
+        Event,
+        WindowEvent,
+    },
+    assets::Handle,
+    renderer::{
+        SpriteSheet,
+        SpriteRender,
+    },
+    window::ScreenDimensions,
+};
+use crate::{
+    recording::{
+        Recording,
+        WorldEvent,
+    },
+    resources::WorldDimensions,
+    simulator::{
+        Simulator,
+        adjust_time_scale,
+    },
+};
+
+const SEEK_SECONDS: f64 = 5.0;
+
+pub struct ReplayState {
+    recording: Recording,
+    entities: HashMap<usize, Entity>,
+    sprite_sheet: Option<Handle<SpriteSheet>>,
+    cursor: f64,
+    frame: Option<usize>,
+    playing: bool,
+    reverse: bool,
+}
+
+impl ReplayState {
+    pub fn new(recording: Recording) -> Self {
+        ReplayState {
+            recording,
+            entities: HashMap::new(),
+            sprite_sheet: None,
+            cursor: 0.0,
+            frame: None,
+            playing: true,
+            reverse: false,
+        }
+    }
+
+    fn show_frame(&mut self, world: &mut World, index: usize) {
+        if self.frame == Some(index) {
+            return
+        }
+        let frame = match self.recording.frames.get(index) {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let present: HashSet<usize> = frame.boids.iter().map(|boid| boid.id).collect();
+        let gone: Vec<usize> = self.entities.keys().filter(|id| !present.contains(id)).cloned().collect();
+        for id in gone {
+            if let Some(entity) = self.entities.remove(&id) {
+                world.delete_entity(entity).ok();
+            }
+        }
+
+        for state in &frame.boids {
+            let entity = match self.entities.get(&state.id) {
+                Some(entity) => *entity,
+                None => {
+                    let scale = self.recording.header.species.get(state.species)
+                        .map_or((0.025, 0.035), |species| species.scale);
+                    let mut transform = Transform::default();
+                    transform.set_scale(Vector3::new(scale.0, scale.1, 0.0));
+                    let mut builder = world.create_entity().with(transform);
+                    if let Some(sprite_sheet) = &self.sprite_sheet {
+                        builder = builder.with(SpriteRender {
+                            sprite_sheet: sprite_sheet.clone(),
+                            sprite_number: 0,
+                        });
+                    }
+                    let entity = builder.build();
+                    self.entities.insert(state.id, entity);
+                    entity
+                },
+            };
+            if let Some(transform) = world.write_storage::<Transform>().get_mut(entity) {
+                let angle = state.velocity[1].atan2(state.velocity[0]) - std::f32::consts::PI / 2.0;
+                transform.set_rotation_2d(angle);
+                transform.set_translation_xyz(state.position[0], state.position[1], state.position[2]);
+            }
+        }
+
+        for event in &frame.events {
+            match event {
+                WorldEvent::Spawned(ids) => println!("[{:.2}s] {} spawned", frame.time, ids.len()),
+                WorldEvent::Died(ids) => println!("[{:.2}s] {} died", frame.time, ids.len()),
+                WorldEvent::Weather(weather) => println!("[{:.2}s] weather {:?}", frame.time, weather),
+                WorldEvent::Flock(event) => println!("[{:.2}s] flock {:?}", frame.time, event),
+            }
+        }
+        self.frame = Some(index);
+    }
+
+    fn seek(&mut self, world: &mut World, time: f64) {
+        let (start, end) = match (self.recording.frames.first(), self.recording.frames.last()) {
+            (Some(first), Some(last)) => (first.time, last.time),
+            _ => return,
+        };
+        self.cursor = time.max(start).min(end);
+        if (self.reverse && self.cursor <= start) || (!self.reverse && self.cursor >= end) {
+            self.playing = false;
+        }
+        let index = self.recording.frame_at(self.cursor);
+        self.show_frame(world, index);
+    }
+
+    fn step(&mut self, world: &mut World) {
+        self.playing = false;
+        let current = self.frame.unwrap_or(0);
+        let index = if self.reverse {
+            current.saturating_sub(1)
+        }
+        else {
+            (current + 1).min(self.recording.frames.len().saturating_sub(1))
+        };
+        if let Some(frame) = self.recording.frames.get(index) {
+            self.cursor = frame.time;
+        }
+        self.show_frame(world, index);
+    }
+}
+
+impl SimpleState for ReplayState {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+        world.insert(WorldDimensions::from_config(&self.recording.header.world));
+        self.sprite_sheet = Some(Simulator::load_spritesheet(world));
+        Simulator::init_camera(world, width, height);
+        println!("Replaying {} frames", self.recording.frames.len());
+        let start = self.recording.frames.first().map_or(0.0, |frame| frame.time);
+        self.seek(world, start);
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match event {
+            StateEvent::Window(event) if is_close_requested(&event) => return Trans::Quit,
+            StateEvent::Input(InputEvent::ActionPressed(action)) => {
+                match action.as_str() {
+                    "pause" => self.playing = !self.playing,
+                    "reverse" => self.reverse = !self.reverse,
+                    "step" => self.step(data.world),
+                    "seek_forward" => {
+                        let time = self.cursor + SEEK_SECONDS;
+                        self.seek(data.world, time);
+                    },
+                    "seek_back" => {
+                        let time = self.cursor - SEEK_SECONDS;
+                        self.seek(data.world, time);
+                    },
+                    _ => adjust_time_scale(data.world, &action),
+                }
+            },
+            StateEvent::Window(Event::WindowEvent { event: WindowEvent::Resized(size), .. }) => {
+                Simulator::resize_camera(data.world, size.width as f32, size.height as f32);
+            },
+            _ => {},
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if self.playing {
+            let delta = data.world.read_resource::<Time>().delta_seconds() as f64;
+            let time = if self.reverse {
+                self.cursor - delta
+            }
+            else {
+                self.cursor + delta
+            };
+            self.seek(data.world, time);
+        }
+        Trans::None
+    }
+}
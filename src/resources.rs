@@ -1,8 +1,46 @@
 use crate::{
     types::MatrixNx3f,
     quadtree::QuadTree,
+    scenario::{
+        Species,
+        Obstacle,
+        Goal,
+    },
+    genome::Genome,
+    boid::Boid,
+    age::Age,
+    energy::Energy,
 };
 use std::ops::Range;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+use amethyst::{
+    assets::Handle,
+    core::math::RowVector3,
+    ecs::prelude::Entity,
+    renderer::SpriteSheet,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldConfig {
+    pub width: f32,
+    pub height: f32,
+    pub region_size: f32,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            width: 2000.0,
+            height: 2000.0,
+            region_size: 250.0,
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct WorldDimensions {
@@ -28,16 +66,20 @@ impl WorldRegion {
     }
 }
 impl WorldDimensions {
-    pub fn new(max_x: f32, min_x: f32, max_y: f32, min_y: f32) -> Self {
-        let x_mid = (min_x + max_x) / 2.0;
-        let y_mid = (min_y + max_y) / 2.0;
-        
-        let regions = vec![
-            WorldRegion::new(min_x..x_mid, min_y..y_mid),
-            WorldRegion::new(x_mid..max_x, min_y..y_mid),
-            WorldRegion::new(min_x..x_mid, y_mid..max_y),
-            WorldRegion::new(x_mid..max_x, y_mid..max_y),
-        ];
+    pub fn new(max_x: f32, min_x: f32, max_y: f32, min_y: f32, region_size: f32) -> Self {
+        let region_size = region_size.max(1.0);
+        let mut regions = vec![];
+        let mut y = min_y;
+        while y < max_y {
+            let y_end = (y + region_size).min(max_y);
+            let mut x = min_x;
+            while x < max_x {
+                let x_end = (x + region_size).min(max_x);
+                regions.push(WorldRegion::new(x..x_end, y..y_end));
+                x = x_end;
+            }
+            y = y_end;
+        }
         
         WorldDimensions {
             min_x,
@@ -47,6 +89,22 @@ impl WorldDimensions {
             regions,
         }
     }
+
+    pub fn from_config(config: &WorldConfig) -> Self {
+        WorldDimensions::new(config.width, 0.0, config.height, 0.0, config.region_size)
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        (self.min_x..self.max_x).contains(&x) && (self.min_y..self.max_y).contains(&y)
+    }
+
+    // Keeps a position inside the half-open world ranges the quadtree accepts.
+    pub fn clamp(&self, mut pos: RowVector3<f32>) -> RowVector3<f32> {
+        let below = |max: f32| max - std::f32::EPSILON * max.abs().max(1.0);
+        pos[0] = pos[0].max(self.min_x).min(below(self.max_x));
+        pos[1] = pos[1].max(self.min_y).min(below(self.max_y));
+        pos
+    }
 }
 
 pub struct BoidInfo {
@@ -68,7 +126,7 @@ impl Default for BoidInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum WeatherType {
     Sunny(f32),
     Cloudy(f32),
@@ -82,7 +140,7 @@ enum Season {
     Spring,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Weather {
     weather: WeatherType,
     temp: f32,
@@ -103,6 +161,189 @@ impl Default for Weather {
     }
 }
 
+impl Weather {
+    pub fn set_weather(&mut self, weather: WeatherType) {
+        self.weather = weather;
+    }
+
+    pub fn get_weather(&self) -> &WeatherType {
+        &self.weather
+    }
+
+    pub fn food_growth_factor(&self) -> f32 {
+        match self.weather {
+            WeatherType::Sunny(_) => 1.0,
+            WeatherType::Cloudy(_) => 0.8,
+            WeatherType::Rainy(_) => 1.5,
+        }
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct SpeciesList(pub Vec<Species>);
+
+impl SpeciesList {
+    pub fn is_predator(&self, species: usize) -> bool {
+        self.0.get(species).map_or(false, |species| species.predator)
+    }
+
+    pub fn has_predators(&self) -> bool {
+        self.0.iter().any(|species| species.predator)
+    }
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Environment {
+    pub obstacles: Vec<Obstacle>,
+    pub goals: Vec<Goal>,
+}
+
+pub struct BoidSprite(pub Handle<SpriteSheet>);
+
+pub struct SpawnRequest {
+    pub position: RowVector3<f32>,
+    pub velocity: RowVector3<f32>,
+    pub species: usize,
+}
+
+#[derive(Default)]
+pub struct SpawnRequests(pub Vec<SpawnRequest>);
+
+#[derive(Debug, Clone)]
+pub struct DeathRecord {
+    pub genome: Genome,
+    pub species: usize,
+    pub age: f32,
+    pub gathered: f32,
+}
+
+impl DeathRecord {
+    pub fn new(boid: &Boid, age: Option<&Age>, energy: Option<&Energy>) -> Self {
+        DeathRecord {
+            genome: boid.get_genome().clone(),
+            species: boid.get_species(),
+            age: age.map_or(0.0, Age::get_seconds),
+            gathered: energy.map_or(0.0, Energy::get_gathered),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DeathLog(pub Vec<DeathRecord>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraMode {
+    Free,
+    Follow,
+    Flock,
+    FitAll,
+}
+
+impl Default for CameraMode {
+    fn default() -> Self {
+        CameraMode::Free
+    }
+}
+
+pub const MIN_ZOOM: f32 = 0.1;
+pub const MAX_ZOOM: f32 = 10.0;
+
+// World units per screen pixel for the orthographic camera, plus the cursor position
+// of an in-progress drag.
+pub struct CameraView {
+    scale: f32,
+    pub drag: Option<(f32, f32)>,
+}
+
+impl CameraView {
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(MIN_ZOOM).min(MAX_ZOOM);
+    }
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        CameraView {
+            scale: 1.0,
+            drag: None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+#[derive(Default)]
+pub struct Selection {
+    pub entity: Option<Entity>,
+    pub neighbours: Vec<Entity>,
+    pub forces: Vec<(&'static str, RowVector3<f32>)>,
+}
+
+impl Selection {
+    pub fn select(&mut self, entity: Option<Entity>) {
+        self.entity = entity;
+        self.neighbours.clear();
+        self.forces.clear();
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimClock {
+    pub tick: u64,
+    pub seconds: f64,
+}
+
+impl SimClock {
+    pub fn advance(&mut self, seconds: f32) {
+        self.tick += 1;
+        self.seconds += seconds as f64;
+    }
+}
+
+// Entity indices are recycled after a death, so recordings and exports use these instead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BoidIds {
+    next: usize,
+}
+
+impl BoidIds {
+    pub fn next(&mut self) -> usize {
+        self.next += 1;
+        self.next
+    }
+}
+
+pub struct WanderSeeder(pub Pcg32);
+
+impl Default for WanderSeeder {
+    fn default() -> Self {
+        WanderSeeder(Pcg32::seed_from_u64(0))
+    }
+}
+
+pub struct SimRng(pub Pcg32);
+
+impl Default for SimRng {
+    fn default() -> Self {
+        SimRng(Pcg32::seed_from_u64(0))
+    }
+}
+
+pub struct VicsekRng(pub Pcg32);
+
+impl Default for VicsekRng {
+    fn default() -> Self {
+        VicsekRng(Pcg32::seed_from_u64(0))
+    }
+}
+
 #[derive(PartialEq)]
 pub enum CurrentState {
     Running,
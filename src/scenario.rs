@@ -0,0 +1,466 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+};
+use rand::Rng;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::core::math::RowVector3;
+use crate::{
+    resources::{
+        WorldConfig,
+        WeatherType,
+    },
+    genome::Genome,
+    steering::{
+        NeuralPolicy,
+        PolicyConfig,
+    },
+    script::{
+        Script,
+        ScenarioEvent,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BoundaryMode {
+    Steer,
+    Wrap,
+}
+
+impl Default for BoundaryMode {
+    fn default() -> Self {
+        BoundaryMode::Steer
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VicsekConfig {
+    pub speed: f32,
+    pub radius: f32,
+    pub noise: f32,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SimulationModel {
+    Reynolds,
+    Vicsek(VicsekConfig),
+}
+
+impl Default for SimulationModel {
+    fn default() -> Self {
+        SimulationModel::Reynolds
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Species {
+    pub name: String,
+    #[serde(default = "Species::default_scale")]
+    pub scale: (f32, f32),
+    #[serde(default)]
+    pub predator: bool,
+    #[serde(default = "Species::default_catch_radius")]
+    pub catch_radius: f32,
+    #[serde(default)]
+    pub genome: Genome,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+}
+
+impl Species {
+    fn default_scale() -> (f32, f32) {
+        (0.025, 0.035)
+    }
+
+    fn default_catch_radius() -> f32 {
+        10.0
+    }
+}
+
+impl Default for Species {
+    fn default() -> Self {
+        Species {
+            name: "boid".to_string(),
+            scale: Species::default_scale(),
+            predator: false,
+            catch_radius: Species::default_catch_radius(),
+            genome: Genome::default(),
+            policy: PolicyConfig::Rules,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SpawnRegion {
+    Point((f32, f32)),
+    Rect {
+        min: (f32, f32),
+        max: (f32, f32),
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+    },
+}
+
+impl SpawnRegion {
+    pub fn bounds(&self) -> ((f32, f32), (f32, f32)) {
+        match *self {
+            SpawnRegion::Point(point) => (point, point),
+            SpawnRegion::Rect { min, max } => (min, max),
+            SpawnRegion::Circle { center, radius } => ((center.0 - radius, center.1 - radius), (center.0 + radius, center.1 + radius)),
+        }
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> RowVector3<f32> {
+        match *self {
+            SpawnRegion::Point((x, y)) => RowVector3::new(x, y, 0.0),
+            SpawnRegion::Rect { min, max } => RowVector3::new(
+                sample_range(rng, min.0, max.0),
+                sample_range(rng, min.1, max.1),
+                0.0),
+            SpawnRegion::Circle { center, radius } => {
+                let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+                let dist = radius * rng.gen::<f32>().sqrt();
+                RowVector3::new(center.0 + dist * angle.cos(), center.1 + dist * angle.sin(), 0.0)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum VelocityDistribution {
+    Zero,
+    Fixed((f32, f32)),
+    Uniform {
+        min: (f32, f32),
+        max: (f32, f32),
+    },
+    RandomHeading {
+        min_speed: f32,
+        max_speed: f32,
+    },
+}
+
+impl Default for VelocityDistribution {
+    fn default() -> Self {
+        VelocityDistribution::Zero
+    }
+}
+
+impl VelocityDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> RowVector3<f32> {
+        match *self {
+            VelocityDistribution::Zero => RowVector3::zeros(),
+            VelocityDistribution::Fixed((x, y)) => RowVector3::new(x, y, 0.0),
+            VelocityDistribution::Uniform { min, max } => RowVector3::new(
+                sample_range(rng, min.0, max.0),
+                sample_range(rng, min.1, max.1),
+                0.0),
+            VelocityDistribution::RandomHeading { min_speed, max_speed } => {
+                let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+                let speed = sample_range(rng, min_speed, max_speed);
+                RowVector3::new(speed * angle.cos(), speed * angle.sin(), 0.0)
+            },
+        }
+    }
+}
+
+fn sample_range<R: Rng>(rng: &mut R, min: f32, max: f32) -> f32 {
+    if min < max {
+        rng.gen_range(min, max)
+    }
+    else {
+        min
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnGroup {
+    pub count: usize,
+    pub region: SpawnRegion,
+    #[serde(default)]
+    pub velocity: VelocityDistribution,
+    #[serde(default)]
+    pub species: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub position: (f32, f32),
+    pub radius: f32,
+}
+
+impl Obstacle {
+    pub fn get_position(&self) -> RowVector3<f32> {
+        RowVector3::new(self.position.0, self.position.1, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Goal {
+    pub position: (f32, f32),
+    #[serde(default = "Goal::default_weight")]
+    pub weight: f32,
+}
+
+impl Goal {
+    fn default_weight() -> f32 {
+        1.0
+    }
+
+    pub fn get_position(&self) -> RowVector3<f32> {
+        RowVector3::new(self.position.0, self.position.1, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoodPatch {
+    pub position: (f32, f32),
+    pub radius: f32,
+    pub capacity: f32,
+    pub regrowth: f32,
+}
+
+impl FoodPatch {
+    pub fn get_position(&self) -> RowVector3<f32> {
+        RowVector3::new(self.position.0, self.position.1, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnergyConfig {
+    #[serde(skip)]
+    pub enabled: bool,
+    pub initial: f32,
+    pub max: f32,
+    pub base_cost: f32,
+    pub speed_cost: f32,
+    pub eat_rate: f32,
+    pub hunger_threshold: f32,
+    pub forage_weight: f32,
+    pub perception: f32,
+}
+
+impl Default for EnergyConfig {
+    fn default() -> Self {
+        EnergyConfig {
+            enabled: false,
+            initial: 50.0,
+            max: 100.0,
+            base_cost: 0.5,
+            speed_cost: 0.02,
+            eat_rate: 20.0,
+            hunger_threshold: 0.6,
+            forage_weight: 1.0,
+            perception: 300.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WanderConfig {
+    #[serde(skip)]
+    pub enabled: bool,
+    pub radius: f32,
+    pub distance: f32,
+    pub jitter: f32,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl Default for WanderConfig {
+    fn default() -> Self {
+        WanderConfig {
+            enabled: false,
+            radius: 5.0,
+            distance: 10.0,
+            jitter: 2.0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    #[serde(skip)]
+    pub enabled: bool,
+    pub interval: u64,
+    pub link_radius: f32,
+    pub min_size: usize,
+    #[serde(default)]
+    pub colour: bool,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        ClusterConfig {
+            enabled: false,
+            interval: 30,
+            link_radius: 50.0,
+            min_size: 3,
+            colour: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopulationConfig {
+    pub max_population: usize,
+    #[serde(default)]
+    pub max_age: Option<f32>,
+    pub birth_threshold: f32,
+    pub offspring_share: f32,
+    pub spawn_radius: f32,
+    #[serde(default)]
+    pub mutation_rate: f32,
+    #[serde(default)]
+    pub mutation_strength: f32,
+}
+
+impl Default for PopulationConfig {
+    fn default() -> Self {
+        PopulationConfig {
+            max_population: 1000,
+            max_age: None,
+            birth_threshold: 0.9,
+            offspring_share: 0.5,
+            spawn_radius: 20.0,
+            mutation_rate: 0.0,
+            mutation_strength: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub world: Option<WorldConfig>,
+    #[serde(default)]
+    pub boundary: BoundaryMode,
+    #[serde(default)]
+    pub model: SimulationModel,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub species: Vec<Species>,
+    #[serde(default)]
+    pub spawn_groups: Vec<SpawnGroup>,
+    #[serde(default)]
+    pub obstacles: Vec<Obstacle>,
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub weather: Option<WeatherType>,
+    #[serde(default)]
+    pub energy: Option<EnergyConfig>,
+    #[serde(default)]
+    pub food: Vec<FoodPatch>,
+    #[serde(default)]
+    pub population: PopulationConfig,
+    #[serde(default)]
+    pub wander: Option<WanderConfig>,
+    #[serde(default)]
+    pub clustering: Option<ClusterConfig>,
+    #[serde(default)]
+    pub script: Option<PathBuf>,
+    #[serde(default)]
+    pub events: Vec<ScenarioEvent>,
+}
+
+impl Scenario {
+    pub fn species_list(&self) -> Vec<Species> {
+        if self.species.is_empty() {
+            vec![Species::default()]
+        }
+        else {
+            self.species.clone()
+        }
+    }
+
+    pub fn species_index(&self, name: Option<&str>) -> Option<usize> {
+        match name {
+            Some(name) => self.species_list().iter().position(|species| species.name == name),
+            None => Some(0),
+        }
+    }
+
+    pub fn validate(&self, world: &WorldConfig) -> Result<(), String> {
+        let inside = |(x, y): (f32, f32)| x >= 0.0 && x <= world.width && y >= 0.0 && y <= world.height;
+        for group in &self.spawn_groups {
+            let (min, max) = group.region.bounds();
+            if !inside(min) || !inside(max) {
+                return Err(format!("Spawn region {:?} lies outside the {}x{} world", group.region, world.width, world.height))
+            }
+        }
+        for obstacle in &self.obstacles {
+            if !inside(obstacle.position) {
+                return Err(format!("Obstacle at {:?} lies outside the {}x{} world", obstacle.position, world.width, world.height))
+            }
+        }
+        for species in &self.species {
+            if let Err(e) = species.policy.check() {
+                return Err(format!("Failed to load steering policy for species {}: {}", species.name, e))
+            }
+            if let Some(network) = &species.genome.network {
+                network.check_shape(NeuralPolicy::INPUTS, NeuralPolicy::OUTPUTS)
+                    .map_err(|e| format!("Genome network for species {} has the wrong shape: {}", species.name, e))?;
+            }
+        }
+        for group in &self.spawn_groups {
+            if self.species_index(group.species.as_ref().map(String::as_str)).is_none() {
+                return Err(format!("Spawn group references unknown species {:?}", group.species))
+            }
+        }
+        if self.script.is_none() && !self.events.is_empty() {
+            return Err("Scenario events need a script to call into".to_string())
+        }
+        Ok(())
+    }
+
+    // Loading runs the script's top-level code, so do it once and hand the result around.
+    pub fn load_script(&self) -> Result<Option<Arc<Script>>, String> {
+        self.script.as_ref().map(|path| Script::load(path).map(Arc::new)).transpose()
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            world: None,
+            boundary: BoundaryMode::Steer,
+            model: SimulationModel::Reynolds,
+            seed: None,
+            species: vec![],
+            spawn_groups: vec![
+                SpawnGroup {
+                    count: 50,
+                    region: SpawnRegion::Rect { min: (50.0, 0.0), max: (150.0, 50.0) },
+                    velocity: VelocityDistribution::Zero,
+                    species: None,
+                },
+                SpawnGroup {
+                    count: 50,
+                    region: SpawnRegion::Rect { min: (350.0, 0.0), max: (450.0, 50.0) },
+                    velocity: VelocityDistribution::Zero,
+                    species: None,
+                },
+            ],
+            obstacles: vec![],
+            goals: vec![],
+            weather: None,
+            energy: None,
+            food: vec![],
+            population: PopulationConfig::default(),
+            wander: None,
+            clustering: None,
+            script: None,
+            events: vec![],
+        }
+    }
+}
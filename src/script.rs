@@ -0,0 +1,233 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+    },
+};
+use rlua::{
+    Context,
+    Function,
+    Lua,
+    Table,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::core::math::RowVector3;
+use crate::{
+    api::ApiCommand,
+    resources::WeatherType,
+    steering::Neighbourhood,
+    sweep::SweepParameter,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioEvent {
+    pub time: f32,
+    pub call: String,
+}
+
+pub struct Script {
+    lua: Mutex<Lua>,
+    actions: Arc<Mutex<Vec<ApiCommand>>>,
+    warned: AtomicBool,
+}
+
+impl Script {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read script {}: {}", path.display(), e))?;
+        let lua = Lua::new();
+        let actions = Arc::new(Mutex::new(vec![]));
+        lua.context(|ctx| {
+            Self::register(ctx, &actions)?;
+            ctx.load(&source).set_name(&path.display().to_string())?.exec()
+        }).map_err(|e| format!("Failed to load script {}: {}", path.display(), e))?;
+        Ok(Script {
+            lua: Mutex::new(lua),
+            actions,
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    fn register(ctx: Context, actions: &Arc<Mutex<Vec<ApiCommand>>>) -> rlua::Result<()> {
+        let globals = ctx.globals();
+
+        let queue = actions.clone();
+        globals.set("spawn", ctx.create_function(move |_, (species, x, y, count): (Option<String>, f32, f32, Option<usize>)| {
+            push(&queue, ApiCommand::Spawn {
+                position: (x, y),
+                velocity: (0.0, 0.0),
+                species,
+                count: count.unwrap_or(1),
+            });
+            Ok(())
+        })?)?;
+
+        let queue = actions.clone();
+        globals.set("set_weather", ctx.create_function(move |_, (kind, temp): (String, f32)| {
+            let weather = match kind.as_str() {
+                "Sunny" => WeatherType::Sunny(temp),
+                "Cloudy" => WeatherType::Cloudy(temp),
+                "Rainy" => WeatherType::Rainy(temp),
+                _ => return Err(rlua::Error::RuntimeError(format!("unknown weather {}", kind))),
+            };
+            push(&queue, ApiCommand::SetWeather { weather });
+            Ok(())
+        })?)?;
+
+        let queue = actions.clone();
+        globals.set("add_obstacle", ctx.create_function(move |_, (x, y, radius): (f32, f32, f32)| {
+            push(&queue, ApiCommand::AddObstacle { position: (x, y), radius });
+            Ok(())
+        })?)?;
+
+        let queue = actions.clone();
+        globals.set("clear_obstacles", ctx.create_function(move |_, ()| {
+            push(&queue, ApiCommand::ClearObstacles);
+            Ok(())
+        })?)?;
+
+        let queue = actions.clone();
+        globals.set("set_parameter", ctx.create_function(move |_, (name, value, species): (String, f32, Option<String>)| {
+            let parameter = SweepParameter::from_name(&name)
+                .ok_or_else(|| rlua::Error::RuntimeError(format!("unknown parameter {}", name)))?;
+            push(&queue, ApiCommand::SetParameter { parameter, species, value });
+            Ok(())
+        })?)?;
+
+        Ok(())
+    }
+
+    fn warn(&self, function: &str, e: rlua::Error) {
+        // Steering functions run once per boid per frame, so only the first failure is reported.
+        if !self.warned.swap(true, Ordering::Relaxed) {
+            println!("Script function {} failed: {}", function, e);
+        }
+    }
+
+    pub fn steer(&self, function: &str, hood: &Neighbourhood) -> RowVector3<f32> {
+        let lua = match self.lua.lock() {
+            Ok(lua) => lua,
+            Err(_) => return RowVector3::zeros(),
+        };
+        let result = lua.context(|ctx| {
+            let steer: Function = ctx.globals().get(function)?;
+            let force: Option<Table> = steer.call((boid_table(ctx, hood)?, neighbour_table(ctx, hood)?))?;
+            match force {
+                Some(force) => Ok(RowVector3::new(force.get::<_, Option<f32>>("x")?.unwrap_or(0.0),
+                                                  force.get::<_, Option<f32>>("y")?.unwrap_or(0.0),
+                                                  0.0)),
+                None => Ok(RowVector3::zeros()),
+            }
+        });
+        result.unwrap_or_else(|e| {
+            self.warn(function, e);
+            RowVector3::zeros()
+        })
+    }
+
+    pub fn call(&self, function: &str, time: f64) {
+        let lua = match self.lua.lock() {
+            Ok(lua) => lua,
+            Err(_) => return,
+        };
+        let result = lua.context(|ctx| {
+            let event: Function = ctx.globals().get(function)?;
+            event.call::<_, ()>(time)
+        });
+        if let Err(e) = result {
+            println!("Script event {} failed: {}", function, e);
+        }
+    }
+
+    pub fn take_actions(&self) -> Vec<ApiCommand> {
+        match self.actions.lock() {
+            Ok(mut actions) => actions.drain(..).collect(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+fn push(queue: &Mutex<Vec<ApiCommand>>, command: ApiCommand) {
+    if let Ok(mut queue) = queue.lock() {
+        queue.push(command);
+    }
+}
+
+fn point_table<'lua>(ctx: Context<'lua>, point: &RowVector3<f32>) -> rlua::Result<Table<'lua>> {
+    let table = ctx.create_table()?;
+    table.set("x", point[0])?;
+    table.set("y", point[1])?;
+    Ok(table)
+}
+
+fn boid_table<'lua>(ctx: Context<'lua>, hood: &Neighbourhood) -> rlua::Result<Table<'lua>> {
+    let boid = point_table(ctx, &hood.position)?;
+    boid.set("vx", hood.velocity[0])?;
+    boid.set("vy", hood.velocity[1])?;
+    boid.set("predator", hood.is_predator)?;
+    boid.set("max_speed", hood.genome.max_speed)?;
+    boid.set("perception_radius", hood.genome.perception_radius)?;
+    boid.set("threats", ctx.create_sequence_from(
+        hood.threats.iter().map(|threat| point_table(ctx, threat)).collect::<rlua::Result<Vec<_>>>()?)?)?;
+    boid.set("prey", ctx.create_sequence_from(
+        hood.prey.iter().map(|prey| point_table(ctx, prey)).collect::<rlua::Result<Vec<_>>>()?)?)?;
+    if let Some(food) = &hood.food {
+        boid.set("food", point_table(ctx, food)?)?;
+    }
+    Ok(boid)
+}
+
+fn neighbour_table<'lua>(ctx: Context<'lua>, hood: &Neighbourhood) -> rlua::Result<Table<'lua>> {
+    let neighbours = hood.positions.row_iter()
+        .zip(hood.velocities.row_iter())
+        .enumerate()
+        .filter(|(i, _)| *i != hood.own_row)
+        .map(|(_, (position, velocity))| {
+            let neighbour = ctx.create_table()?;
+            neighbour.set("x", position[0])?;
+            neighbour.set("y", position[1])?;
+            neighbour.set("vx", velocity[0])?;
+            neighbour.set("vy", velocity[1])?;
+            Ok(neighbour)
+        })
+        .collect::<rlua::Result<Vec<_>>>()?;
+    ctx.create_sequence_from(neighbours)
+}
+
+#[derive(Default)]
+pub struct ScriptEvents {
+    pub script: Option<Arc<Script>>,
+    events: Vec<ScenarioEvent>,
+    next: usize,
+}
+
+impl ScriptEvents {
+    pub fn new(script: Option<Arc<Script>>, mut events: Vec<ScenarioEvent>) -> Self {
+        events.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+        ScriptEvents {
+            script,
+            events,
+            next: 0,
+        }
+    }
+
+    pub fn skip_until(&mut self, seconds: f64) {
+        self.next = self.events.iter().take_while(|event| event.time as f64 <= seconds).count();
+    }
+
+    pub fn due(&mut self, seconds: f64) -> Vec<ScenarioEvent> {
+        let start = self.next;
+        self.skip_until(seconds);
+        self.events[start..self.next.max(start)].to_vec()
+    }
+}
@@ -0,0 +1,35 @@
+use amethyst::{
+    prelude::*,
+    core::SystemDesc,
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+    },
+};
+use crate::{
+    api::PendingCommands,
+    resources::SimClock,
+    script::ScriptEvents,
+};
+
+#[derive(SystemDesc)]
+pub struct ScriptSystem;
+impl<'s> System<'s> for ScriptSystem {
+    type SystemData = (Write<'s, ScriptEvents>,
+                       Write<'s, PendingCommands>,
+                       Read<'s, SimClock>);
+
+    fn run(&mut self, (mut events, mut pending, clock): Self::SystemData) {
+        let script = match events.script.clone() {
+            Some(script) => script,
+            None => return,
+        };
+        for event in events.due(clock.seconds) {
+            script.call(&event.call, clock.seconds);
+        }
+        pending.0.extend(script.take_actions());
+    }
+}
@@ -1,19 +1,50 @@
-use rand::Rng;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::Arc,
+};
+use rand::{
+    Rng,
+    SeedableRng,
+};
+use rand_pcg::Pcg32;
 use amethyst::{
     prelude::*,
     core::{
         transform::Transform,
         math::{
+            Point2,
+            Vector2,
             Vector3,
+            RowVector3,
         },
+        geometry::Plane,
         timing::Time,
     },
+    input::{
+        is_close_requested,
+        InputEvent,
+        InputHandler,
+        StringBindings,
+    },
+    config::Config,
+    ecs::prelude::{
+        Join,
+        Entity,
+    },
+    winit::{
+        Event,
+        WindowEvent,
+    },
     assets::{
         AssetStorage,
         Loader,
         Handle,
     },
     renderer::{
+        palette::Srgba,
+        resources::Tint,
+        camera::Projection,
         Camera,
         ImageFormat,
         SpriteSheet,
@@ -25,59 +56,408 @@ use amethyst::{
 };
 use crate::{
     boid::Boid,
+    energy::Energy,
+    food::Food,
+    age::Age,
+    wander::Wander,
     resources::{
         BoidInfo,
+        BoidIds,
         WorldDimensions,
+        WorldConfig,
         CurrentState,
+        SpeciesList,
+        Environment,
+        Weather,
+        BoidSprite,
+        CameraView,
+        DebugOverlay,
+        WanderSeeder,
+        VicsekRng,
+        SimRng,
+        SimClock,
+        SpawnRequest,
+        SpawnRequests,
+        Selection,
+    },
+    scenario::{
+        Scenario,
+        EnergyConfig,
+        BoundaryMode,
+        WanderConfig,
+        SimulationModel,
+        ClusterConfig,
+        Obstacle,
+        SpawnRegion,
+        VelocityDistribution,
+    },
+    boid_system::BoidSystem,
+    steering::SteeringPolicies,
+    behaviors::PipelineConfig,
+    quadtree::QuadTree,
+    paused::Paused,
+    metrics::Metrics,
+    flock::Flocks,
+    recording::Recorder,
+    snapshot::Snapshot,
+    export::Exporter,
+    columnar::ColumnarExporter,
+    api::ApiServer,
+    inspector::Inspector,
+    script::{
+        Script,
+        ScriptEvents,
     },
 };
 
-pub struct Simulator;
+const SPAWN_GROUP_SIZE: usize = 10;
+const SPAWN_GROUP_RADIUS: f32 = 30.0;
+const OBSTACLE_RADIUS: f32 = 40.0;
+const SELECT_RADIUS: f32 = 25.0;
+const RECENT_FLOCK_EVENTS: usize = 10;
+const MIN_TIME_SCALE: f32 = 0.25;
+const MAX_TIME_SCALE: f32 = 8.0;
+
+pub fn adjust_time_scale(world: &mut World, action: &str) {
+    let mut time = world.write_resource::<Time>();
+    let scale = match action {
+        "speed_up" => time.time_scale() * 2.0,
+        "slow_down" => time.time_scale() / 2.0,
+        "reset_speed" => 1.0,
+        _ => return,
+    };
+    time.set_time_scale(scale.max(MIN_TIME_SCALE).min(MAX_TIME_SCALE));
+}
+
+pub struct Simulator {
+    scenario: Scenario,
+    world_config: PathBuf,
+    record: Option<PathBuf>,
+    export: Option<(PathBuf, u64, Option<HashSet<usize>>)>,
+    parquet: Option<(PathBuf, u64)>,
+    snapshot: Option<Snapshot>,
+    script: Option<Arc<Script>>,
+    api: Option<(u16, u64)>,
+}
+
 impl Simulator {
-    fn init_camera(world: &mut World, width: f32, height: f32) {
+    pub fn new(scenario: Scenario, world_config: PathBuf) -> Self {
+        Simulator {
+            scenario,
+            world_config,
+            record: None,
+            export: None,
+            parquet: None,
+            snapshot: None,
+            script: None,
+            api: None,
+        }
+    }
+
+    pub fn with_script(mut self, script: Option<Arc<Script>>) -> Self {
+        self.script = script;
+        self
+    }
+
+    pub fn with_recording(mut self, path: Option<PathBuf>) -> Self {
+        self.record = path;
+        self
+    }
+
+    pub fn with_export(mut self, path: Option<PathBuf>, interval: u64, ids: Option<HashSet<usize>>) -> Self {
+        self.export = path.map(|path| (path, interval, ids));
+        self
+    }
+
+    pub fn with_parquet(mut self, dir: Option<PathBuf>, batch_ticks: u64) -> Self {
+        self.parquet = dir.map(|dir| (dir, batch_ticks));
+        self
+    }
+
+    pub fn with_api(mut self, port: Option<u16>, interval: u64) -> Self {
+        self.api = port.map(|port| (port, interval));
+        self
+    }
+
+    pub fn with_snapshot(mut self, snapshot: Snapshot) -> Self {
+        self.scenario = snapshot.scenario.clone();
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    fn save_snapshot(&self, world: &World) {
+        let snapshot = Snapshot::capture(world, &self.scenario);
+        let path = PathBuf::from(format!("snapshot-{}.bin", snapshot.clock.tick));
+        match snapshot.save(&path) {
+            Ok(()) => println!("Saved snapshot to {}", path.display()),
+            Err(e) => println!("Failed to save snapshot to {}: {}", path.display(), e),
+        }
+    }
+
+    pub fn orthographic_camera(width: f32, height: f32, scale: f32) -> Camera {
+        let (half_width, half_height) = (width * scale * 0.5, height * scale * 0.5);
+        Camera::from(Projection::orthographic(-half_width, half_width, -half_height, half_height, 0.1, 2000.0))
+    }
+
+    pub fn init_camera(world: &mut World, width: f32, height: f32) {
+        let scale = world.try_fetch::<CameraView>().map_or(1.0, |view| view.get_scale());
         let mut transform = Transform::default();
         transform.set_translation_xyz(width * 0.5, height * 0.5, 550.0);
         world.create_entity()
-            .with(Camera::standard_3d(width, height))
+            .with(Self::orthographic_camera(width, height, scale))
             .with(transform)
             .build();
     }
 
-    fn init_boids(world: &mut World, sprite_sheet: &Handle<SpriteSheet>, boid_num: usize) {
-        let sprite_render = SpriteRender {
-            sprite_sheet: sprite_sheet.clone(),
-            sprite_number: 0,
+    pub fn resize_camera(world: &mut World, width: f32, height: f32) {
+        let scale = world.try_fetch::<CameraView>().map_or(1.0, |view| view.get_scale());
+        let mut cameras = world.write_storage::<Camera>();
+        for camera in (&mut cameras).join() {
+            *camera = Self::orthographic_camera(width, height, scale);
+        }
+    }
+
+    pub fn screen_to_world(camera: &Camera, transform: &Transform, screen: &ScreenDimensions, position: (f32, f32)) -> Option<RowVector3<f32>> {
+        let ray = camera.projection().screen_ray(Point2::new(position.0, position.1),
+                                                 Vector2::new(screen.width(), screen.height()),
+                                                 transform);
+        let distance = ray.intersect_plane(&Plane::with_z(0.0))?;
+        let point = ray.at_distance(distance);
+        Some(RowVector3::new(point.x, point.y, 0.0))
+    }
+
+    pub fn toggle_debug_overlay(world: &mut World) {
+        let mut overlay = world.write_resource::<DebugOverlay>();
+        overlay.enabled = !overlay.enabled;
+    }
+
+    fn cursor_position(world: &World) -> Option<RowVector3<f32>> {
+        let mouse = world.read_resource::<InputHandler<StringBindings>>().mouse_position()?;
+        let screen = world.read_resource::<ScreenDimensions>();
+        let cameras = world.read_storage::<Camera>();
+        let transforms = world.read_storage::<Transform>();
+        let (camera, transform) = (&cameras, &transforms).join().next()?;
+        Self::screen_to_world(camera, transform, &screen, mouse)
+    }
+
+    fn spawn_group(world: &mut World) {
+        let center = match Self::cursor_position(world) {
+            Some(center) => center,
+            None => return,
         };
-        let mut transform = Transform::default();
-        let mut rng = rand::thread_rng();
-        let position = Vector3::new(
-            if boid_num >= 50 {
-                rng.gen_range(350.0, 450.0)
+        let region = SpawnRegion::Circle { center: (center[0], center[1]), radius: SPAWN_GROUP_RADIUS };
+        let velocity = VelocityDistribution::RandomHeading { min_speed: 10.0, max_speed: 30.0 };
+        let requests: Vec<SpawnRequest> = {
+            let mut rng = world.write_resource::<SimRng>();
+            (0..SPAWN_GROUP_SIZE)
+                .map(|_| SpawnRequest {
+                    position: region.sample(&mut rng.0),
+                    velocity: velocity.sample(&mut rng.0),
+                    species: 0,
+                })
+                .collect()
+        };
+        world.write_resource::<SpawnRequests>().0.extend(requests);
+    }
+
+    pub fn select_at_cursor(world: &mut World) {
+        let cursor = match Self::cursor_position(world) {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let nearest = {
+            let boids = world.read_storage::<Boid>();
+            let entities = world.entities();
+            (&entities, &boids).join()
+                .map(|(entity, boid)| (entity, (boid.get_position() - cursor).magnitude()))
+                .filter(|(_, distance)| *distance < SELECT_RADIUS)
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                .map(|(entity, _)| entity)
+        };
+        world.write_resource::<Selection>().select(nearest);
+    }
+
+    fn place_obstacle(world: &mut World) {
+        if let Some(position) = Self::cursor_position(world) {
+            world.write_resource::<Environment>().obstacles.push(Obstacle {
+                position: (position[0], position[1]),
+                radius: OBSTACLE_RADIUS,
+            });
+        }
+    }
+
+    fn resize_world(world: &mut World, config: &WorldConfig) {
+        let world_dim = WorldDimensions::from_config(config);
+        {
+            let wrap = *world.read_resource::<BoundaryMode>() == BoundaryMode::Wrap;
+            let mut boids = world.write_storage::<Boid>();
+            let mut transforms = world.write_storage::<Transform>();
+            for (boid, transform) in (&mut boids, &mut transforms).join() {
+                let mut pos = boid.get_position();
+                if !world_dim.contains(pos[0], pos[1]) {
+                    if wrap {
+                        BoidSystem::wrap_position(&mut pos, &world_dim);
+                    }
+                    else {
+                        pos = world_dim.clamp(pos);
+                    }
+                    boid.set_position(pos);
+                    transform.set_translation_xyz(pos[0], pos[1], pos[2]);
+                }
+            }
+        }
+        world.insert(world_dim);
+        world.insert(config.clone());
+        Self::rebuild_spatial_index(world);
+    }
+
+    pub fn rebuild_spatial_index(world: &mut World) {
+        let mut qt = {
+            let world_dim = world.read_resource::<WorldDimensions>();
+            QuadTree::new(world_dim.min_x..world_dim.max_x, world_dim.min_y..world_dim.max_y)
+        };
+        for boid in (&world.read_storage::<Boid>()).join() {
+            if !qt.insert(boid.clone()) {
+                println!("Boid {} at {} is outside the world and won't move", boid.id, boid.get_position());
             }
-            else {
-                rng.gen_range(50.0, 150.0)
-            }, rng.gen_range(0.0, 50.0), 0.0);
-        transform.set_translation(position);
-        transform.set_scale(Vector3::new(0.025, 0.035, 0.0));
-
-        let mut boid = Boid::new(position.transpose(), boid_num);
-        //boid.set_id(boid_num);
-        let entity = world.create_entity()
-            .with(sprite_render)
+        }
+        world.write_resource::<BoidInfo>().qt = qt;
+    }
+
+    pub fn init_boid(world: &mut World, sprite_sheet: Option<&Handle<SpriteSheet>>, scale: (f32, f32), mut boid: Boid, energy: Option<Energy>, age: Age, wander: Option<Wander>) -> Entity {
+        // BoidSystem only moves boids it finds in the quadtree, which rejects anything outside the world.
+        if let Some(world_dim) = world.try_fetch::<WorldDimensions>() {
+            boid.set_position(world_dim.clamp(boid.get_position()));
+        }
+        let mut transform = Transform::default();
+        transform.set_translation(boid.get_position().transpose());
+        transform.set_scale(Vector3::new(scale.0, scale.1, 0.0));
+
+        let mut builder = world.create_entity()
             .with(boid.clone())
-            .with(transform)
-            .build();
-        world.write_component::<Boid>().get_mut(entity).unwrap().set_id(entity.id() as usize);
+            .with(transform);
+        if let Some(sprite_sheet) = sprite_sheet {
+            builder = builder.with(SpriteRender {
+                sprite_sheet: sprite_sheet.clone(),
+                sprite_number: 0,
+            });
+        }
+        if let Some(energy) = energy {
+            builder = builder.with(energy);
+        }
+        if let Some(wander) = wander {
+            builder = builder.with(wander);
+        }
+        builder = builder.with(age);
+        let entity = builder.build();
+        world.write_component::<Boid>().get_mut(entity).unwrap().set_entity(entity);
         {
             let fetched = world.try_fetch_mut::<BoidInfo>();
-            boid.set_id(entity.id() as usize);
+            boid.set_entity(entity);
             if let Some(mut res) = fetched {
-                res.qt.insert(boid);
+                if !res.qt.insert(boid) {
+                    println!("Failed to add boid {} to the spatial index", entity.id());
+                }
             };
         }
+        entity
+    }
+
+    pub fn init_food(world: &mut World, sprite_sheet: Option<&Handle<SpriteSheet>>, food: Food) {
+        let mut transform = Transform::default();
+        transform.set_translation(food.get_position().transpose());
+        transform.set_scale(Vector3::new(food.get_radius() / 128.0, food.get_radius() / 128.0, 0.0));
+        let mut builder = world.create_entity()
+            .with(food)
+            .with(transform);
+        if let Some(sprite_sheet) = sprite_sheet {
+            builder = builder
+                .with(SpriteRender {
+                    sprite_sheet: sprite_sheet.clone(),
+                    sprite_number: 0,
+                })
+                .with(Tint(Srgba::new(0.2, 0.8, 0.2, 0.5)));
+        }
+        builder.build();
+    }
+
+    pub fn init_resources(world: &mut World, scenario: &Scenario, config: &WorldConfig, script: Option<Arc<Script>>) {
+        world.insert(WorldDimensions::from_config(config));
+        world.insert(BoidInfo::new(config.width, 0.0, config.height, 0.0));
+        world.insert(config.clone());
+        world.insert(SimClock::default());
+        world.insert(BoidIds::default());
+        world.insert(SimRng(Pcg32::seed_from_u64(scenario.seed.unwrap_or_else(|| rand::thread_rng().gen()))));
+
+        let species_list = scenario.species_list();
+        let policies = {
+            let mut rng = world.write_resource::<SimRng>();
+            species_list.iter()
+                .map(|species| species.policy.build(species.predator, script.as_ref(), &mut rng.0).unwrap_or_else(|e| {
+                    println!("Falling back to rule-based steering for {}: {}", species.name, e);
+                    Box::new(PipelineConfig::reynolds(species.predator).build(script.as_ref()))
+                }))
+                .collect()
+        };
+        world.insert(ScriptEvents::new(script, scenario.events.clone()));
+        world.insert(SteeringPolicies::new(policies));
+        world.insert(SpeciesList(species_list));
+        world.insert(scenario.boundary.clone());
+        world.insert(VicsekRng(Pcg32::seed_from_u64(match &scenario.model {
+            SimulationModel::Vicsek(config) => config.seed,
+            _ => 0,
+        })));
+        world.insert(scenario.model.clone());
+        world.insert(Environment {
+            obstacles: scenario.obstacles.clone(),
+            goals: scenario.goals.clone(),
+        });
+        let mut weather = Weather::default();
+        if let Some(preset) = &scenario.weather {
+            weather.set_weather(preset.clone());
+        }
+        world.insert(weather);
+        world.insert(scenario.energy.clone()
+                     .map(|config| EnergyConfig { enabled: true, ..config })
+                     .unwrap_or_default());
+        world.insert(scenario.wander.clone()
+                     .map(|config| WanderConfig { enabled: true, ..config })
+                     .unwrap_or_default());
+        world.insert(WanderSeeder(Pcg32::seed_from_u64(scenario.wander.as_ref().map_or(0, |config| config.seed))));
+        world.insert(scenario.clustering.clone()
+                     .map(|config| ClusterConfig { enabled: true, ..config })
+                     .unwrap_or_default());
+        world.insert(Flocks::default());
+        world.insert(scenario.population.clone());
+    }
+
+    pub fn init_scenario(world: &mut World, scenario: &Scenario, config: &WorldConfig, script: Option<Arc<Script>>, sprite_sheet: Option<&Handle<SpriteSheet>>) {
+        Self::init_resources(world, scenario, config, script);
+
+        let species_list = scenario.species_list();
+        for group in &scenario.spawn_groups {
+            let species_idx = scenario.species_index(group.species.as_ref().map(String::as_str)).unwrap_or(0);
+            let species = &species_list[species_idx];
+            for _ in 0..group.count {
+                let (position, velocity) = {
+                    let mut rng = world.write_resource::<SimRng>();
+                    (group.region.sample(&mut rng.0), group.velocity.sample(&mut rng.0))
+                };
+                let mut boid = Boid::new(position, world.write_resource::<BoidIds>().next());
+                boid.set_velocity(velocity);
+                boid.set_species(species_idx);
+                boid.set_genome(species.genome.clone());
+                let energy = scenario.energy.as_ref().map(|config| Energy::new(config.initial, config.max));
+                let wander = scenario.wander.as_ref().map(|_| Wander::spawn(&mut world.write_resource::<WanderSeeder>().0));
+                Self::init_boid(world, sprite_sheet, species.scale, boid, energy, Age::default(), wander);
+            }
+        }
+        for patch in &scenario.food {
+            Self::init_food(world, sprite_sheet, Food::new(patch.get_position(), patch.radius, patch.capacity, patch.regrowth));
+        }
     }
 
-    fn load_spritesheet(world: &mut World) -> Handle<SpriteSheet> {
+    pub fn load_spritesheet(world: &mut World) -> Handle<SpriteSheet> {
         let loader = world.read_resource::<Loader>();
         let texture_handle = {
             let texture_storage = world.read_resource::<AssetStorage<Texture>>();
@@ -90,29 +470,118 @@ impl Simulator {
 
 impl SimpleState for Simulator {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
-        let nboids = 100;
         let world = data.world;
         let (width, height) = {
             let dim = world.read_resource::<ScreenDimensions>();
             (dim.width(), dim.height())
         };
-        println!("{}x{}", width, height);
-        world.insert(WorldDimensions::new(width, 0.0, height, 0.0));
-        world.insert(BoidInfo::new(width, 0.0, height, 0.0));
-
         let sprite_handle = Self::load_spritesheet(world);
-        for i in 0..nboids {
-            Self::init_boids(world, &sprite_handle, i);
+        let config = match self.snapshot.take() {
+            Some(snapshot) => {
+                snapshot.restore(world, self.script.clone(), Some(&sprite_handle));
+                snapshot.world
+            },
+            None => {
+                let config = self.scenario.world.clone().unwrap_or_else(|| WorldConfig::load(&self.world_config));
+                Self::init_scenario(world, &self.scenario, &config, self.script.clone(), Some(&sprite_handle));
+                config
+            },
+        };
+        world.insert(BoidSprite(sprite_handle));
+        if let Some(path) = &self.record {
+            match Recorder::create(path, &config, &self.scenario.species_list()) {
+                Ok(recorder) => world.insert(recorder),
+                Err(e) => println!("Failed to start recording to {}: {}", path.display(), e),
+            }
+        }
+        if let Some((path, interval, ids)) = &self.export {
+            match Exporter::create(path, *interval, ids.clone()) {
+                Ok(exporter) => world.insert(exporter),
+                Err(e) => println!("Failed to start export to {}: {}", path.display(), e),
+            }
+        }
+        if let Some((dir, batch_ticks)) = &self.parquet {
+            match ColumnarExporter::create(dir, *batch_ticks) {
+                Ok(exporter) => world.insert(exporter),
+                Err(e) => println!("Failed to start parquet export to {}: {}", dir.display(), e),
+            }
         }
+        if let Some((port, interval)) = self.api {
+            match ApiServer::start(port, interval) {
+                Ok(server) => world.insert(server),
+                Err(e) => println!("Failed to start API on port {}: {}", port, e),
+            }
+        }
+        let inspector = Inspector::create(world);
+        world.insert(inspector);
         Self::init_camera(world, width, height);
+        *world.write_resource::<CurrentState>() = CurrentState::Running;
     }
 
-    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
-        if data.world.read_resource::<Time>().absolute_time_seconds() % 15.0 < 0.01 {
-            *data.world.write_resource::<CurrentState>() = CurrentState::Running;
+    fn on_resume(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        *data.world.write_resource::<CurrentState>() = CurrentState::Running;
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        if let Some(mut recorder) = data.world.try_fetch_mut::<Recorder>() {
+            if let Err(e) = recorder.flush() {
+                println!("Failed to flush recording: {}", e);
+            }
+        }
+        if let Some(mut exporter) = data.world.try_fetch_mut::<Exporter>() {
+            if let Err(e) = exporter.flush() {
+                println!("Failed to flush export: {}", e);
+            }
         }
-        else {
-            *data.world.write_resource::<CurrentState>() = CurrentState::Paused;
+        if let Some(mut exporter) = data.world.try_fetch_mut::<ColumnarExporter>() {
+            if let Err(e) = exporter.close() {
+                println!("Failed to finish parquet export: {}", e);
+            }
+        }
+    }
+
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match event {
+            StateEvent::Window(event) if is_close_requested(&event) => return Trans::Quit,
+            StateEvent::Input(InputEvent::ActionPressed(action)) => {
+                match action.as_str() {
+                    "pause" => return Trans::Push(Box::new(Paused::default())),
+                    "reload_world" => {
+                        let config = self.scenario.world.clone().unwrap_or_else(|| WorldConfig::load(&self.world_config));
+                        Self::resize_world(data.world, &config);
+                    },
+                    "save_snapshot" => self.save_snapshot(data.world),
+                    "spawn_group" => Self::spawn_group(data.world),
+                    "place_obstacle" => Self::place_obstacle(data.world),
+                    "select" => Self::select_at_cursor(data.world),
+                    "debug_overlay" => Self::toggle_debug_overlay(data.world),
+                    "print_metrics" => {
+                        println!("{}", *data.world.read_resource::<Metrics>());
+                        let now = data.world.read_resource::<SimClock>().seconds;
+                        let flocks = data.world.read_resource::<Flocks>();
+                        for flock in &flocks.flocks {
+                            println!("  flock {} species={} size={} born={:.1}s age={:.1}s", flock.id, flock.species, flock.size, flock.born, now - flock.born);
+                        }
+                        let recent = flocks.events.len().saturating_sub(RECENT_FLOCK_EVENTS);
+                        for (time, event) in flocks.events.iter().skip(recent) {
+                            println!("  [{:.1}s] {:?}", time, event);
+                        }
+                    },
+                    _ => adjust_time_scale(data.world, &action),
+                }
+            },
+            StateEvent::Window(Event::WindowEvent { event: WindowEvent::Resized(size), .. }) => {
+                Self::resize_camera(data.world, size.width as f32, size.height as f32);
+            },
+            _ => {},
+        }
+        Trans::None
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let request = data.world.try_fetch_mut::<ApiServer>().and_then(|mut server| server.take_pause_request());
+        if request == Some(true) {
+            return Trans::Push(Box::new(Paused::default()))
         }
         Trans::None
     }
@@ -0,0 +1,185 @@
+use std::{
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+    },
+    path::Path,
+    sync::Arc,
+};
+use rand_pcg::Pcg32;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::{
+    prelude::*,
+    assets::Handle,
+    core::timing::Time,
+    ecs::prelude::Join,
+    renderer::SpriteSheet,
+};
+use crate::{
+    boid::Boid,
+    energy::Energy,
+    food::Food,
+    age::Age,
+    wander::Wander,
+    flock::{
+        Flock,
+        Flocks,
+    },
+    resources::{
+        WorldConfig,
+        Weather,
+        SimClock,
+        BoidIds,
+        Environment,
+        SpeciesList,
+        SimRng,
+        WanderSeeder,
+        VicsekRng,
+    },
+    scenario::{
+        Scenario,
+        SimulationModel,
+        WanderConfig,
+    },
+    steering::{
+        Network,
+        NeuralPolicy,
+        SteeringPolicies,
+    },
+    simulator::Simulator,
+    script::{
+        Script,
+        ScriptEvents,
+    },
+};
+
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BoidSnapshot {
+    pub boid: Boid,
+    pub energy: Option<Energy>,
+    pub age: Age,
+    pub wander: Option<Wander>,
+    pub flock: Option<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub scenario: Scenario,
+    pub world: WorldConfig,
+    pub weather: Weather,
+    pub clock: SimClock,
+    pub boid_ids: BoidIds,
+    pub time_scale: f32,
+    // Obstacles, genomes and model settings can all be changed at runtime through the API or a script.
+    pub environment: Environment,
+    pub species: SpeciesList,
+    pub wander_config: WanderConfig,
+    pub model: SimulationModel,
+    pub sim_rng: Pcg32,
+    pub wander_seeder: Pcg32,
+    pub vicsek_rng: Pcg32,
+    pub networks: Vec<Option<Network>>,
+    pub flocks: Flocks,
+    pub boids: Vec<BoidSnapshot>,
+    pub food: Vec<Food>,
+}
+
+impl Snapshot {
+    pub fn capture(world: &World, scenario: &Scenario) -> Self {
+        let boids = world.read_storage::<Boid>();
+        let energies = world.read_storage::<Energy>();
+        let ages = world.read_storage::<Age>();
+        let wanders = world.read_storage::<Wander>();
+        let flock_ids = world.read_storage::<Flock>();
+        let entities = world.entities();
+
+        Snapshot {
+            version: FORMAT_VERSION,
+            scenario: scenario.clone(),
+            world: (*world.read_resource::<WorldConfig>()).clone(),
+            weather: (*world.read_resource::<Weather>()).clone(),
+            clock: (*world.read_resource::<SimClock>()).clone(),
+            boid_ids: (*world.read_resource::<BoidIds>()).clone(),
+            time_scale: world.read_resource::<Time>().time_scale(),
+            environment: (*world.read_resource::<Environment>()).clone(),
+            species: (*world.read_resource::<SpeciesList>()).clone(),
+            wander_config: (*world.read_resource::<WanderConfig>()).clone(),
+            model: (*world.read_resource::<SimulationModel>()).clone(),
+            sim_rng: world.read_resource::<SimRng>().0.clone(),
+            wander_seeder: world.read_resource::<WanderSeeder>().0.clone(),
+            vicsek_rng: world.read_resource::<VicsekRng>().0.clone(),
+            networks: world.read_resource::<SteeringPolicies>().policies.iter()
+                .map(|policy| policy.network().cloned())
+                .collect(),
+            flocks: (*world.read_resource::<Flocks>()).clone(),
+            boids: (&entities, &boids).join()
+                .map(|(entity, boid)| BoidSnapshot {
+                    boid: boid.clone(),
+                    energy: energies.get(entity).cloned(),
+                    age: ages.get(entity).cloned().unwrap_or_default(),
+                    wander: wanders.get(entity).cloned(),
+                    flock: flock_ids.get(entity).map(Flock::get_id),
+                })
+                .collect(),
+            food: world.read_storage::<Food>().join().cloned().collect(),
+        }
+    }
+
+    pub fn restore(&self, world: &mut World, script: Option<Arc<Script>>, sprite_sheet: Option<&Handle<SpriteSheet>>) {
+        Simulator::init_resources(world, &self.scenario, &self.world, script);
+        {
+            let mut policies = world.write_resource::<SteeringPolicies>();
+            for (policy, network) in policies.policies.iter_mut().zip(self.networks.iter()) {
+                if let Some(network) = network {
+                    *policy = Box::new(NeuralPolicy::new(network.clone()));
+                }
+            }
+        }
+        world.insert(self.weather.clone());
+        world.insert(self.clock.clone());
+        world.insert(self.boid_ids.clone());
+        world.write_resource::<Time>().set_time_scale(self.time_scale);
+        world.insert(self.environment.clone());
+        world.insert(self.species.clone());
+        // Whether wander is on comes from the scenario and isn't serialized.
+        let wander_enabled = world.read_resource::<WanderConfig>().enabled;
+        world.insert(WanderConfig { enabled: wander_enabled, ..self.wander_config.clone() });
+        world.insert(self.model.clone());
+        world.write_resource::<ScriptEvents>().skip_until(self.clock.seconds);
+        world.insert(SimRng(self.sim_rng.clone()));
+        world.insert(WanderSeeder(self.wander_seeder.clone()));
+        world.insert(VicsekRng(self.vicsek_rng.clone()));
+        world.insert(self.flocks.clone());
+
+        let species_list = self.scenario.species_list();
+        for saved in &self.boids {
+            let scale = species_list.get(saved.boid.get_species()).map_or((0.025, 0.035), |species| species.scale);
+            let entity = Simulator::init_boid(world, sprite_sheet, scale, saved.boid.clone(), saved.energy.clone(), saved.age.clone(), saved.wander.clone());
+            if let Some(id) = saved.flock {
+                world.write_storage::<Flock>().insert(entity, Flock::new(id)).ok();
+            }
+        }
+        for food in &self.food {
+            Simulator::init_food(world, sprite_sheet, food.clone());
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), bincode::Error> {
+        bincode::serialize_into(BufWriter::new(File::create(path)?), self)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, bincode::Error> {
+        let snapshot: Snapshot = bincode::deserialize_from(BufReader::new(File::open(path)?))?;
+        if snapshot.version != FORMAT_VERSION {
+            return Err(Box::new(bincode::ErrorKind::Custom(format!("Unsupported snapshot version {}", snapshot.version))))
+        }
+        Ok(snapshot)
+    }
+}
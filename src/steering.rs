@@ -0,0 +1,300 @@
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::Arc,
+};
+use rand::Rng;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::{
+    core::math::RowVector3,
+    config::{
+        Config,
+        ConfigError,
+    },
+};
+use crate::{
+    script::Script,
+    genome::Genome,
+    resources::{
+        WorldDimensions,
+        Environment,
+    },
+    scenario::{
+        BoundaryMode,
+        EnergyConfig,
+        WanderConfig,
+    },
+    types::MatrixNx3f,
+    behaviors::{
+        SteeringBehavior,
+        Boundary,
+        BehaviorPipeline,
+        PipelineConfig,
+    },
+};
+
+pub struct Neighbourhood<'a> {
+    pub position: RowVector3<f32>,
+    pub velocity: RowVector3<f32>,
+    pub genome: &'a Genome,
+    pub is_predator: bool,
+    pub positions: &'a MatrixNx3f,
+    pub velocities: &'a MatrixNx3f,
+    pub own_row: usize,
+    pub threats: &'a [RowVector3<f32>],
+    pub prey: &'a [RowVector3<f32>],
+    pub food: Option<RowVector3<f32>>,
+    pub energy_config: &'a EnergyConfig,
+    pub wander: Option<f32>,
+    pub wander_config: &'a WanderConfig,
+    pub environment: &'a Environment,
+    pub world_dim: &'a WorldDimensions,
+    pub boundary: &'a BoundaryMode,
+}
+
+impl<'a> Neighbourhood<'a> {
+    pub fn has_neighbours(&self) -> bool {
+        self.positions.nrows() > 1
+    }
+}
+
+pub trait SteeringPolicy: Send + Sync {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32>;
+
+    // Same as steer, but also lists the parts the force was built from for the inspector.
+    fn steer_explained(&self, hood: &Neighbourhood, _parts: &mut Vec<(&'static str, RowVector3<f32>)>) -> RowVector3<f32> {
+        self.steer(hood)
+    }
+
+    fn network(&self) -> Option<&Network> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PolicyConfig {
+    Rules,
+    Pipeline(PipelineConfig),
+    Neural {
+        #[serde(default)]
+        weights: Option<PathBuf>,
+        #[serde(default = "PolicyConfig::default_hidden")]
+        hidden: usize,
+    },
+}
+
+impl PolicyConfig {
+    fn default_hidden() -> usize {
+        8
+    }
+
+    fn load_weights(path: &Path) -> Result<Network, ConfigError> {
+        let network = Network::load_no_fallback(path)?;
+        network.check_shape(NeuralPolicy::INPUTS, NeuralPolicy::OUTPUTS)
+            .map_err(|e| ConfigError::File(io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))))?;
+        Ok(network)
+    }
+
+    // Untrained networks draw their weights from the caller's rng so seeded runs repeat.
+    pub fn network<R: Rng>(&self, rng: &mut R) -> Result<Option<Network>, ConfigError> {
+        match self {
+            PolicyConfig::Rules | PolicyConfig::Pipeline(_) => Ok(None),
+            PolicyConfig::Neural { weights: Some(path), .. } => Self::load_weights(path).map(Some),
+            PolicyConfig::Neural { weights: None, hidden } => {
+                Ok(Some(Network::random(rng, &[NeuralPolicy::INPUTS, *hidden, NeuralPolicy::OUTPUTS])))
+            },
+        }
+    }
+
+    pub fn check(&self) -> Result<(), ConfigError> {
+        match self {
+            PolicyConfig::Neural { weights: Some(path), .. } => Self::load_weights(path).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn build<R: Rng>(&self, predator: bool, script: Option<&Arc<Script>>, rng: &mut R) -> Result<Box<dyn SteeringPolicy>, ConfigError> {
+        Ok(match self {
+            PolicyConfig::Rules => Box::new(PipelineConfig::reynolds(predator).build(script)),
+            PolicyConfig::Pipeline(pipeline) => Box::new(pipeline.build(script)),
+            PolicyConfig::Neural { .. } => match self.network(rng)? {
+                Some(network) => Box::new(NeuralPolicy::new(network)),
+                None => Box::new(PipelineConfig::reynolds(predator).build(script)),
+            },
+        })
+    }
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        PolicyConfig::Rules
+    }
+}
+
+pub struct SteeringPolicies {
+    pub policies: Vec<Box<dyn SteeringPolicy>>,
+    fallback: [BehaviorPipeline; 2],
+}
+
+impl SteeringPolicies {
+    pub fn new(policies: Vec<Box<dyn SteeringPolicy>>) -> Self {
+        SteeringPolicies {
+            policies,
+            fallback: [PipelineConfig::reynolds(false).build(None), PipelineConfig::reynolds(true).build(None)],
+        }
+    }
+
+    pub fn get(&self, species: usize, predator: bool) -> &dyn SteeringPolicy {
+        self.policies.get(species).map_or(&self.fallback[predator as usize], |policy| policy.as_ref())
+    }
+}
+
+impl Default for SteeringPolicies {
+    fn default() -> Self {
+        SteeringPolicies::new(vec![])
+    }
+}
+
+pub fn nearest(boid_pos: RowVector3<f32>, points: &[RowVector3<f32>]) -> Option<RowVector3<f32>> {
+    points.iter()
+        .min_by(|a, b| (*a - boid_pos).magnitude_squared()
+                .partial_cmp(&(*b - boid_pos).magnitude_squared())
+                .unwrap_or(std::cmp::Ordering::Equal))
+        .cloned()
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Layer {
+    pub weights: Vec<Vec<f32>>,
+    pub biases: Vec<f32>,
+}
+
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Network {
+    pub layers: Vec<Layer>,
+}
+
+impl Network {
+    pub fn random<R: Rng>(rng: &mut R, sizes: &[usize]) -> Self {
+        let layers = sizes.windows(2)
+            .map(|pair| {
+                let scale = 1.0 / (pair[0] as f32).sqrt();
+                Layer {
+                    weights: (0..pair[1])
+                        .map(|_| (0..pair[0]).map(|_| rng.gen_range(-scale, scale)).collect())
+                        .collect(),
+                    biases: vec![0.0; pair[1]],
+                }
+            })
+            .collect();
+        Network {
+            layers,
+        }
+    }
+
+    pub fn check_shape(&self, inputs: usize, outputs: usize) -> Result<(), String> {
+        let mut width = inputs;
+        for (i, layer) in self.layers.iter().enumerate() {
+            if layer.weights.iter().any(|row| row.len() != width) {
+                return Err(format!("layer {} expects {} inputs per neuron", i, width))
+            }
+            if layer.biases.len() != layer.weights.len() {
+                return Err(format!("layer {} has {} neurons but {} biases", i, layer.weights.len(), layer.biases.len()))
+            }
+            width = layer.weights.len();
+        }
+        if self.layers.is_empty() || width != outputs {
+            return Err(format!("network must end in {} outputs, found {}", outputs, width))
+        }
+        Ok(())
+    }
+
+    pub fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+        self.layers.iter().fold(inputs.to_vec(), |activations, layer| {
+            layer.weights.iter()
+                .zip(layer.biases.iter())
+                .map(|(row, bias)| {
+                    let sum: f32 = row.iter().zip(activations.iter()).map(|(w, a)| w * a).sum();
+                    (sum + bias).tanh()
+                })
+                .collect()
+        })
+    }
+
+    pub fn mutate<R: Rng>(&self, rng: &mut R, rate: f32, strength: f32) -> Network {
+        let mut mutated = self.clone();
+        for layer in &mut mutated.layers {
+            for value in layer.weights.iter_mut().flatten().chain(layer.biases.iter_mut()) {
+                if rng.gen::<f32>() < rate {
+                    *value += rng.gen_range(-strength, strength);
+                }
+            }
+        }
+        mutated
+    }
+}
+
+pub struct NeuralPolicy {
+    network: Network,
+}
+
+impl NeuralPolicy {
+    pub const INPUTS: usize = 14;
+    pub const OUTPUTS: usize = 2;
+
+    pub fn new(network: Network) -> Self {
+        NeuralPolicy {
+            network,
+        }
+    }
+
+    fn features(hood: &Neighbourhood) -> Vec<f32> {
+        let radius = hood.genome.perception_radius.max(1.0);
+        let max_speed = hood.genome.max_speed.max(1.0);
+        let others = (hood.positions.nrows() - 1).max(1) as f32;
+
+        let (centroid, heading) = if hood.has_neighbours() {
+            (((hood.positions.row_sum() - hood.position).unscale(others) - hood.position).unscale(radius),
+             ((hood.velocities.row_sum() - hood.velocity).unscale(others) - hood.velocity).unscale(max_speed))
+        }
+        else {
+            (RowVector3::zeros(), RowVector3::zeros())
+        };
+        let neighbours: Vec<RowVector3<f32>> = hood.positions.row_iter()
+            .enumerate()
+            .filter(|(i, _)| *i != hood.own_row)
+            .map(|(_, row)| row.into())
+            .collect();
+        let relative = |target: Option<RowVector3<f32>>| target.map_or(RowVector3::zeros(), |target| (target - hood.position).unscale(radius));
+        let closest = relative(nearest(hood.position, &neighbours));
+        let threat = relative(nearest(hood.position, if hood.is_predator { hood.prey } else { hood.threats }));
+        let food = relative(hood.food);
+        let velocity = hood.velocity.unscale(max_speed);
+        let boundary = Boundary.steer(hood).unscale(10.0);
+
+        [centroid, heading, closest, velocity, threat, food, boundary].iter()
+            .flat_map(|v| vec![v[0], v[1]])
+            .collect()
+    }
+}
+
+impl SteeringPolicy for NeuralPolicy {
+    fn steer(&self, hood: &Neighbourhood) -> RowVector3<f32> {
+        let network = hood.genome.network.as_deref().unwrap_or(&self.network);
+        let outputs = network.forward(&Self::features(hood));
+        match outputs.as_slice() {
+            [x, y] => RowVector3::new(*x, *y, 0.0) * hood.genome.max_speed,
+            _ => RowVector3::zeros(),
+        }
+    }
+
+    fn network(&self) -> Option<&Network> {
+        Some(&self.network)
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    env,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::PathBuf,
+    process::{
+        Child,
+        Command,
+    },
+    thread,
+    time::Duration,
+};
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::config::Config;
+use crate::{
+    headless::RunSummary,
+    genome::Genome,
+    scenario::{
+        Scenario,
+        SimulationModel,
+        WanderConfig,
+    },
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum SweepParameter {
+    Cohesion,
+    Alignment,
+    Separation,
+    SeparationRadius,
+    PerceptionRadius,
+    MaxSpeed,
+    WanderJitter,
+    VicsekNoise,
+    VicsekRadius,
+    VicsekSpeed,
+}
+
+impl SweepParameter {
+    fn name(self) -> &'static str {
+        match self {
+            SweepParameter::Cohesion => "cohesion",
+            SweepParameter::Alignment => "alignment",
+            SweepParameter::Separation => "separation",
+            SweepParameter::SeparationRadius => "separation_radius",
+            SweepParameter::PerceptionRadius => "perception_radius",
+            SweepParameter::MaxSpeed => "max_speed",
+            SweepParameter::WanderJitter => "wander_jitter",
+            SweepParameter::VicsekNoise => "vicsek_noise",
+            SweepParameter::VicsekRadius => "vicsek_radius",
+            SweepParameter::VicsekSpeed => "vicsek_speed",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            SweepParameter::Cohesion,
+            SweepParameter::Alignment,
+            SweepParameter::Separation,
+            SweepParameter::SeparationRadius,
+            SweepParameter::PerceptionRadius,
+            SweepParameter::MaxSpeed,
+            SweepParameter::WanderJitter,
+            SweepParameter::VicsekNoise,
+            SweepParameter::VicsekRadius,
+            SweepParameter::VicsekSpeed,
+        ].iter().cloned().find(|parameter| parameter.name() == name)
+    }
+
+    fn is_genome(self) -> bool {
+        match self {
+            SweepParameter::WanderJitter | SweepParameter::VicsekNoise | SweepParameter::VicsekRadius | SweepParameter::VicsekSpeed => false,
+            _ => true,
+        }
+    }
+
+    fn apply(self, scenario: &mut Scenario, species: Option<&str>, value: f32) {
+        if scenario.species.is_empty() {
+            scenario.species = scenario.species_list();
+        }
+        for config in scenario.species.iter_mut().filter(|config| species.map_or(true, |name| config.name == name)) {
+            self.apply_genome(&mut config.genome, value);
+        }
+        if let Some(wander) = scenario.wander.as_mut() {
+            self.apply_wander(wander, value);
+        }
+        self.apply_model(&mut scenario.model, value);
+    }
+
+    pub fn apply_genome(self, genome: &mut Genome, value: f32) {
+        match self {
+            SweepParameter::Cohesion => genome.cohesion = value,
+            SweepParameter::Alignment => genome.alignment = value,
+            SweepParameter::Separation => genome.separation = value,
+            SweepParameter::SeparationRadius => genome.separation_radius = value,
+            SweepParameter::PerceptionRadius => genome.perception_radius = value,
+            SweepParameter::MaxSpeed => genome.max_speed = value,
+            _ => {},
+        }
+    }
+
+    pub fn apply_wander(self, wander: &mut WanderConfig, value: f32) {
+        if let SweepParameter::WanderJitter = self {
+            wander.jitter = value;
+        }
+    }
+
+    pub fn apply_model(self, model: &mut SimulationModel, value: f32) {
+        if let SimulationModel::Vicsek(vicsek) = model {
+            match self {
+                SweepParameter::VicsekNoise => vicsek.noise = value,
+                SweepParameter::VicsekRadius => vicsek.radius = value,
+                SweepParameter::VicsekSpeed => vicsek.speed = value,
+                _ => {},
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SweepValues {
+    Range {
+        min: f32,
+        max: f32,
+        steps: usize,
+    },
+    List(Vec<f32>),
+}
+
+impl SweepValues {
+    fn values(&self) -> Vec<f32> {
+        match self {
+            SweepValues::Range { min, max, steps } if *steps > 1 => (0..*steps)
+                .map(|i| min + (max - min) * i as f32 / (*steps - 1) as f32)
+                .collect(),
+            SweepValues::Range { min, .. } => vec![*min],
+            SweepValues::List(values) => values.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepAxis {
+    pub parameter: SweepParameter,
+    #[serde(default)]
+    pub species: Option<String>,
+    pub values: SweepValues,
+}
+
+impl SweepAxis {
+    // Catches axes that would leave every run identical to the base scenario.
+    fn check(&self, base: &Scenario) -> Result<(), String> {
+        let parameter = self.parameter.name();
+        let vicsek = match base.model {
+            SimulationModel::Vicsek(_) => true,
+            _ => false,
+        };
+        match self.parameter {
+            SweepParameter::WanderJitter if base.wander.is_none() =>
+                return Err(format!("{} needs a scenario with wander", parameter)),
+            SweepParameter::VicsekNoise | SweepParameter::VicsekRadius | SweepParameter::VicsekSpeed if !vicsek =>
+                return Err(format!("{} needs a scenario using the Vicsek model", parameter)),
+            _ if self.parameter.is_genome() && vicsek =>
+                return Err(format!("{} has no effect in the Vicsek model", parameter)),
+            _ => {},
+        }
+        if let Some(name) = &self.species {
+            if !self.parameter.is_genome() {
+                return Err(format!("{} does not apply per species", parameter))
+            }
+            if base.species_index(Some(name)).is_none() {
+                return Err(format!("{} names unknown species {}", parameter, name))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepConfig {
+    pub scenario: PathBuf,
+    pub axes: Vec<SweepAxis>,
+    #[serde(default = "SweepConfig::default_seeds")]
+    pub seeds: Vec<u64>,
+    pub duration: f32,
+    #[serde(default = "SweepConfig::default_time_step")]
+    pub time_step: f32,
+    #[serde(default = "SweepConfig::default_jobs")]
+    pub jobs: usize,
+    pub output: PathBuf,
+}
+
+impl SweepConfig {
+    fn default_seeds() -> Vec<u64> {
+        vec![0]
+    }
+
+    fn default_time_step() -> f32 {
+        1.0 / 60.0
+    }
+
+    fn default_jobs() -> usize {
+        4
+    }
+
+    fn combinations(&self) -> Vec<(Vec<f32>, u64)> {
+        let mut combinations = vec![vec![]];
+        for axis in &self.axes {
+            combinations = combinations.into_iter()
+                .flat_map(|values: Vec<f32>| axis.values.values().into_iter().map(move |value| {
+                    let mut values = values.clone();
+                    values.push(value);
+                    values
+                }))
+                .collect();
+        }
+        combinations.into_iter()
+            .flat_map(|values| self.seeds.iter().map(move |seed| (values.clone(), *seed)))
+            .collect()
+    }
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        SweepConfig {
+            scenario: PathBuf::from("scenarios/default.ron"),
+            axes: vec![],
+            seeds: SweepConfig::default_seeds(),
+            duration: 60.0,
+            time_step: SweepConfig::default_time_step(),
+            jobs: SweepConfig::default_jobs(),
+            output: PathBuf::from("sweep.csv"),
+        }
+    }
+}
+
+struct Run {
+    index: usize,
+    values: Vec<f32>,
+    seed: u64,
+    summary: PathBuf,
+}
+
+pub fn run_sweep(config: &SweepConfig) -> io::Result<()> {
+    let base = Scenario::load_no_fallback(&config.scenario)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    for axis in &config.axes {
+        axis.check(&base).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    }
+    let run_dir = config.output.with_extension("runs");
+    fs::create_dir_all(&run_dir)?;
+    let exe = env::current_exe()?;
+
+    let mut pending = vec![];
+    for (index, (values, seed)) in config.combinations().into_iter().enumerate() {
+        let mut scenario = base.clone();
+        for (axis, value) in config.axes.iter().zip(values.iter()) {
+            axis.parameter.apply(&mut scenario, axis.species.as_ref().map(String::as_str), *value);
+        }
+        scenario.seed = Some(seed);
+        if let Some(wander) = scenario.wander.as_mut() {
+            wander.seed = seed;
+        }
+        if let SimulationModel::Vicsek(vicsek) = &mut scenario.model {
+            vicsek.seed = seed;
+        }
+        let scenario_path = run_dir.join(format!("run-{}.ron", index));
+        scenario.write(&scenario_path)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        pending.push((Run {
+            index,
+            values,
+            seed,
+            summary: run_dir.join(format!("run-{}.json", index)),
+        }, scenario_path));
+    }
+    pending.reverse();
+
+    let total = pending.len();
+    let mut running: Vec<(Run, Child)> = vec![];
+    let mut finished = vec![];
+    while !pending.is_empty() || !running.is_empty() {
+        while running.len() < config.jobs.max(1) {
+            let (run, scenario_path) = match pending.pop() {
+                Some(next) => next,
+                None => break,
+            };
+            let child = Command::new(&exe)
+                .arg("--scenario").arg(&scenario_path)
+                .arg("--headless").arg(config.duration.to_string())
+                .arg("--time-step").arg(config.time_step.to_string())
+                .arg("--summary").arg(&run.summary)
+                .spawn()?;
+            running.push((run, child));
+        }
+
+        let mut still_running = vec![];
+        for (run, mut child) in running {
+            match child.try_wait()? {
+                Some(status) => {
+                    if !status.success() {
+                        println!("Run {} exited with {}", run.index, status);
+                    }
+                    finished.push(run);
+                    println!("Finished {}/{} runs", finished.len(), total);
+                },
+                None => still_running.push((run, child)),
+            }
+        }
+        running = still_running;
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    finished.sort_by_key(|run| run.index);
+    write_results(config, &finished)
+}
+
+fn write_results(config: &SweepConfig, runs: &[Run]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(&config.output)?);
+    let mut header = vec!["run".to_string()];
+    header.extend(config.axes.iter().map(|axis| match &axis.species {
+        Some(species) => format!("{}.{}", species, axis.parameter.name()),
+        None => axis.parameter.name().to_string(),
+    }));
+    header.extend(["seed", "ticks", "population", "polarization", "milling", "nearest_neighbour", "flock_radius", "speed_mean", "flocks"]
+                  .iter().map(|column| column.to_string()));
+    writeln!(writer, "{}", header.join(","))?;
+    for run in runs {
+        let summary: RunSummary = match File::open(&run.summary).map_err(serde_json::Error::io).and_then(serde_json::from_reader) {
+            Ok(summary) => summary,
+            Err(e) => {
+                println!("Missing summary for run {}: {}", run.index, e);
+                continue;
+            },
+        };
+        let mut row = vec![run.index.to_string()];
+        row.extend(run.values.iter().map(f32::to_string));
+        row.extend(vec![
+            run.seed.to_string(),
+            summary.ticks.to_string(),
+            summary.population.to_string(),
+            summary.polarization.to_string(),
+            summary.milling.to_string(),
+            summary.nearest_neighbour.to_string(),
+            summary.flock_radius.to_string(),
+            summary.speed_mean.to_string(),
+            summary.flocks.to_string(),
+        ]);
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    println!("Wrote {} sweep results to {}", runs.len(), config.output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scenario::VicsekConfig;
+    use super::*;
+
+    fn axis(parameter: SweepParameter, species: Option<&str>, values: SweepValues) -> SweepAxis {
+        SweepAxis {
+            parameter,
+            species: species.map(str::to_string),
+            values,
+        }
+    }
+
+    fn vicsek() -> Scenario {
+        Scenario {
+            model: SimulationModel::Vicsek(VicsekConfig { speed: 1.0, radius: 1.0, noise: 0.1, seed: 0 }),
+            ..Scenario::default()
+        }
+    }
+
+    #[test]
+    fn range_includes_both_ends() {
+        assert_eq!(SweepValues::Range { min: 1.0, max: 2.0, steps: 3 }.values(), vec![1.0, 1.5, 2.0]);
+        assert_eq!(SweepValues::Range { min: 1.0, max: 2.0, steps: 1 }.values(), vec![1.0]);
+    }
+
+    #[test]
+    fn combinations_cover_every_value_and_seed() {
+        let config = SweepConfig {
+            axes: vec![
+                axis(SweepParameter::Cohesion, None, SweepValues::List(vec![1.0, 2.0])),
+                axis(SweepParameter::MaxSpeed, None, SweepValues::List(vec![3.0, 4.0, 5.0])),
+            ],
+            seeds: vec![7, 8],
+            ..SweepConfig::default()
+        };
+        let combinations = config.combinations();
+        assert_eq!(combinations.len(), 12);
+        assert_eq!(combinations[0], (vec![1.0, 3.0], 7));
+        assert_eq!(combinations[11], (vec![2.0, 5.0], 8));
+    }
+
+    #[test]
+    fn rejects_axes_the_base_scenario_ignores() {
+        let reynolds = Scenario::default();
+        let values = || SweepValues::List(vec![1.0]);
+        assert!(axis(SweepParameter::Cohesion, None, values()).check(&reynolds).is_ok());
+        assert!(axis(SweepParameter::Cohesion, Some("boid"), values()).check(&reynolds).is_ok());
+        assert!(axis(SweepParameter::Cohesion, Some("shark"), values()).check(&reynolds).is_err());
+        assert!(axis(SweepParameter::WanderJitter, None, values()).check(&reynolds).is_err());
+        assert!(axis(SweepParameter::VicsekNoise, None, values()).check(&reynolds).is_err());
+        assert!(axis(SweepParameter::VicsekNoise, None, values()).check(&vicsek()).is_ok());
+        assert!(axis(SweepParameter::VicsekNoise, Some("boid"), values()).check(&vicsek()).is_err());
+        assert!(axis(SweepParameter::Cohesion, None, values()).check(&vicsek()).is_err());
+    }
+}
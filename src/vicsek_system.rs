@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+use rand::Rng;
+use amethyst::{
+    prelude::*,
+    core::{
+        transform::Transform,
+        math::RowVector3,
+        timing::Time,
+        SystemDesc,
+    },
+    derive::SystemDesc,
+    ecs::prelude::{
+        System,
+        SystemData,
+        Read,
+        Write,
+        WriteStorage,
+        Entities,
+        Entity,
+        Join,
+    },
+};
+use crate::{
+    boid::Boid,
+    boid_system::BoidSystem,
+    behaviors::Boundary,
+    resources::{
+        WorldDimensions,
+        BoidInfo,
+        VicsekRng,
+        Selection,
+    },
+    scenario::{
+        BoundaryMode,
+        SimulationModel,
+    },
+    quadtree::QuadTree,
+};
+
+#[derive(SystemDesc)]
+pub struct VicsekSystem;
+impl VicsekSystem {
+    fn mean_heading(qt: &mut QuadTree, boid_pos: RowVector3<f32>, radius: f32, world_dim: &WorldDimensions, boundary: &BoundaryMode,
+                    mut neighbours: Option<&mut Vec<Entity>>) -> Option<f32> {
+        let width = world_dim.max_x - world_dim.min_x;
+        let height = world_dim.max_y - world_dim.min_y;
+        let offsets: &[f32] = match boundary {
+            BoundaryMode::Wrap => &[-1.0, 0.0, 1.0],
+            BoundaryMode::Steer => &[0.0],
+        };
+        let mut sum = RowVector3::<f32>::zeros();
+        // Ghost windows overlap once the radius exceeds half the world, so count each boid once
+        // at its nearest periodic image.
+        let mut seen = HashSet::new();
+        for dx in offsets {
+            for dy in offsets {
+                let center = boid_pos + RowVector3::new(dx * width, dy * height, 0.0);
+                for other in qt.search_range(center[0] - radius..center[0] + radius, center[1] - radius..center[1] + radius) {
+                    if !seen.insert(other.get_entity()) {
+                        continue;
+                    }
+                    let mut offset = other.get_position() - boid_pos;
+                    if *boundary == BoundaryMode::Wrap {
+                        offset[0] -= width * (offset[0] / width).round();
+                        offset[1] -= height * (offset[1] / height).round();
+                    }
+                    let vel = other.get_velocity();
+                    if offset.magnitude() > radius {
+                        continue;
+                    }
+                    if vel.magnitude_squared() > 0.0 {
+                        sum += vel.normalize();
+                    }
+                    if let (Some(neighbours), Some(entity)) = (neighbours.as_mut(), other.get_entity()) {
+                        neighbours.push(entity);
+                    }
+                }
+            }
+        }
+
+        if sum.magnitude_squared() > 0.0 {
+            Some(sum[1].atan2(sum[0]))
+        }
+        else {
+            None
+        }
+    }
+}
+
+impl<'s> System<'s> for VicsekSystem {
+    type SystemData = (Write<'s, BoidInfo>,
+                       WriteStorage<'s, Transform>,
+                       WriteStorage<'s, Boid>,
+                       Read<'s, WorldDimensions>,
+                       Read<'s, SimulationModel>,
+                       Read<'s, BoundaryMode>,
+                       Write<'s, VicsekRng>,
+                       Write<'s, Selection>,
+                       Read<'s, Time>,
+                       Entities<'s>);
+
+    fn run(&mut self, (mut boid_info, mut locals, mut boids, world_dim, model, boundary, mut rng, mut selection, time, entities): Self::SystemData) {
+        let config = match &*model {
+            SimulationModel::Vicsek(config) => config,
+            _ => return,
+        };
+
+        let mut qt = boid_info.qt.clone();
+        let mut headings = vec![];
+        for (entity, boid) in (&entities, &boids).join() {
+            let selected = selection.entity == Some(entity);
+            let mut neighbours = vec![];
+            let heading = match Self::mean_heading(&mut qt, boid.get_position(), config.radius, &world_dim, &boundary, if selected { Some(&mut neighbours) } else { None }) {
+                Some(heading) => heading,
+                None => rng.0.gen_range(-std::f32::consts::PI, std::f32::consts::PI),
+            };
+            let noise = if config.noise > 0.0 {
+                rng.0.gen_range(-config.noise / 2.0, config.noise / 2.0)
+            }
+            else {
+                0.0
+            };
+            if selected {
+                neighbours.retain(|other| *other != entity);
+                selection.neighbours = neighbours;
+                selection.forces = vec![("alignment", RowVector3::new(heading.cos(), heading.sin(), 0.0) * config.speed)];
+            }
+            headings.push((entity, heading + noise));
+        }
+
+        let mut new_qt = QuadTree::new(world_dim.min_x..world_dim.max_x, world_dim.min_y..world_dim.max_y);
+        for (entity, heading) in headings {
+            let (boid, transform) = match (boids.get_mut(entity), locals.get_mut(entity)) {
+                (Some(boid), Some(transform)) => (boid, transform),
+                _ => continue,
+            };
+
+            let selected = selection.entity == Some(entity);
+            let mut velocity = RowVector3::new(heading.cos(), heading.sin(), 0.0) * config.speed;
+            if *boundary == BoundaryMode::Steer {
+                // Turn away from the walls but keep the model's constant speed.
+                let push = Boundary::limit_area(boid.get_position(), world_dim.max_x, world_dim.max_y, world_dim.min_x, world_dim.min_y);
+                if selected {
+                    selection.forces.push(("boundary", push));
+                }
+                velocity += push;
+                velocity = velocity.try_normalize(std::f32::EPSILON).unwrap_or_else(RowVector3::zeros) * config.speed;
+            }
+            if selected {
+                selection.forces.push(("total", velocity));
+            }
+            let mut boid_pos = boid.get_position() + velocity * time.delta_seconds();
+            match *boundary {
+                BoundaryMode::Wrap => BoidSystem::wrap_position(&mut boid_pos, &world_dim),
+                BoundaryMode::Steer => boid_pos = world_dim.clamp(boid_pos),
+            }
+
+            transform.set_rotation_2d(velocity[1].atan2(velocity[0]) - std::f32::consts::PI / 2.0);
+            transform.set_translation(boid_pos.transpose());
+
+            boid.set_velocity(velocity);
+            boid.set_position(boid_pos);
+            new_qt.insert(boid.clone());
+        }
+        boid_info.qt = new_qt;
+    }
+}
@@ -0,0 +1,48 @@
+use rand::{
+    Rng,
+    SeedableRng,
+};
+use rand_pcg::Pcg32;
+use serde::{
+    Serialize,
+    Deserialize,
+};
+use amethyst::ecs::prelude::{
+    Component,
+    VecStorage,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Wander {
+    rng: Pcg32,
+    angle: f32,
+}
+
+impl Wander {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Pcg32::seed_from_u64(seed);
+        let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+        Wander {
+            rng,
+            angle,
+        }
+    }
+
+    pub fn spawn(seeder: &mut Pcg32) -> Self {
+        Wander::new(seeder.gen())
+    }
+
+    pub fn jitter(&mut self, amount: f32) {
+        if amount > 0.0 {
+            self.angle += self.rng.gen_range(-amount, amount);
+        }
+    }
+
+    pub fn get_angle(&self) -> f32 {
+        self.angle
+    }
+}
+
+impl Component for Wander {
+    type Storage = VecStorage<Self>;
+}